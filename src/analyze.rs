@@ -0,0 +1,162 @@
+use std::{error::Error, fs, io::IsTerminal, path::PathBuf, time::Instant};
+
+use crate::{
+    logic::{self, Search},
+    render::Grid,
+    requests::Turn,
+    strategy,
+};
+
+pub struct Options {
+    /// A `/move` request body
+    pub turn: PathBuf,
+    /// Strategy spec as taken by the arena
+    pub strategy: String,
+    /// Seeds the search instead of the game id
+    pub seed: Option<u64>,
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut turn = None;
+        let mut strategy = "minimax".to_string();
+        let mut seed = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--strategy" => strategy = args.next().ok_or("missing value for --strategy")?,
+                "--seed" => {
+                    seed = Some(
+                        args.next()
+                            .and_then(|v| v.parse().ok())
+                            .ok_or("--seed takes a number")?,
+                    )
+                }
+                _ if turn.is_none() && !arg.starts_with("--") => turn = Some(arg.into()),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        Ok(Self {
+            turn: turn.ok_or("missing turn file")?,
+            strategy,
+            seed,
+        })
+    }
+}
+
+/// Runs the strategy on the turn in `options` and prints the board, the move and, for
+/// strategies that search, every candidate with its score and line.
+pub fn run(options: &Options) -> Result<Option<Search>, Box<dyn Error>> {
+    let strategy = strategy::from_spec(&options.strategy)?;
+    if let Some(seed) = options.seed {
+        if !logic::force_seed(seed) {
+            return Err("searches were already seeded".into());
+        }
+    }
+    let turn: Turn = fs::read_to_string(&options.turn)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        .map_err(|e| format!("failed to read {}: {}", options.turn.display(), e))?;
+    let color = std::io::stdout().is_terminal();
+
+    let started = Instant::now();
+    let Some(search) = strategy.search(turn.clone()) else {
+        let movement = strategy.get_move(turn.clone()).movement;
+        print!(
+            "{}",
+            Grid::from_turn(&turn).with_move(movement).render(color)
+        );
+        println!("move:  {:?} ({})", movement, options.strategy);
+        return Ok(None);
+    };
+    let elapsed = started.elapsed();
+
+    print!(
+        "{}",
+        Grid::from_turn(&turn)
+            .with_move(search.movement)
+            .render(color)
+    );
+    println!("move:  {:?} ({})", search.movement, options.strategy);
+    println!("depth: {}", search.depth);
+    println!("pv:    {}", logic::line(search.pv()));
+
+    let stats = &search.stats;
+    println!(
+        "nodes {} expanded {} branching {:.2} tt hits {} cutoffs {}",
+        stats.nodes,
+        stats.expanded,
+        stats.branching(),
+        stats.tt_hits,
+        stats.cutoffs
+    );
+    println!(
+        "took {:?}, {:.0} nodes/s",
+        elapsed,
+        stats.nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+
+    println!();
+    for candidate in &search.candidates {
+        let chosen = if candidate.movement == search.movement {
+            '*'
+        } else {
+            ' '
+        };
+        println!(
+            "{} {:<5} {:>20}  {}",
+            chosen,
+            format!("{:?}", candidate.movement),
+            candidate.score,
+            logic::line(&candidate.pv)
+        );
+    }
+    if search.candidates.is_empty() {
+        println!("no safe move");
+    }
+
+    Ok(Some(search))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{logic::Ply, parse::parse};
+
+    use super::*;
+
+    fn turn_file(name: &str, board: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("battle_mulle-{}-{}.json", name, std::process::id()));
+        let turn = parse(board).unwrap();
+        fs::write(&path, serde_json::to_string(&turn).unwrap()).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn analyzes_turn_file() {
+        let path = turn_file("analyze", "...\n.A.\n.a.\nB..");
+
+        let options = Options::parse([path].into_iter()).unwrap();
+        let search = run(&options).unwrap().unwrap();
+
+        assert!(search.pv().len() > 2);
+        assert_eq!(search.pv()[0], Ply::You(search.movement));
+        assert!(matches!(search.pv()[1], Ply::Others(_)));
+        for candidate in &search.candidates {
+            assert_eq!(candidate.pv[0], Ply::You(candidate.movement));
+        }
+    }
+
+    #[test]
+    fn strategies_without_search() {
+        let path = turn_file("analyze-random", "...\n.A.\n.a.\nB..");
+
+        let args = [path, "--strategy".to_string(), "random".to_string()];
+        let options = Options::parse(args.into_iter()).unwrap();
+
+        assert!(run(&options).unwrap().is_none());
+        assert!(Options::parse(["--strategy".to_string()].into_iter()).is_err());
+        assert!(Options::parse(["--seed".to_string(), "x".to_string()].into_iter()).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+
+use crate::{
+    engine::{check_board, Eliminated, Game, Rules},
+    requests::{Board, RulesetSettings},
+    responses::Movement,
+    strategy::{self, Strategy},
+};
+
+pub struct Options {
+    /// Strategy specs, one snake each
+    pub snakes: Vec<String>,
+    pub width: i32,
+    pub height: i32,
+    pub rules: Rules,
+    pub settings: RulesetSettings,
+    pub seed: u64,
+    pub games: usize,
+    pub max_turns: u32,
+    pub log: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            snakes: Vec::new(),
+            width: 11,
+            height: 11,
+            rules: Rules::Standard,
+            settings: RulesetSettings::default(),
+            seed: 0,
+            games: 1,
+            max_turns: 1000,
+            log: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", flag))?;
+            options.set(&flag, value)?;
+        }
+
+        if options.snakes.is_empty() {
+            return Err("at least one --snake is required".to_string());
+        }
+        check_board(options.width, options.height, options.snakes.len())?;
+
+        Ok(options)
+    }
+
+    pub fn set(&mut self, flag: &str, value: String) -> Result<(), String> {
+        let invalid = |_| format!("invalid value for {}: {}", flag, value);
+
+        match flag {
+            "--snake" => self.snakes.push(value),
+            "--width" => self.width = value.parse().map_err(invalid)?,
+            "--height" => self.height = value.parse().map_err(invalid)?,
+            "--ruleset" => self.rules = value.parse()?,
+            "--minimum-food" => self.settings.minimum_food = value.parse().map_err(invalid)?,
+            "--food-spawn-chance" => {
+                self.settings.food_spawn_chance = value.parse().map_err(invalid)?
+            }
+            "--hazard-damage" => {
+                self.settings.hazard_damage_per_turn = value.parse().map_err(invalid)?
+            }
+            "--shrink-every" => {
+                self.settings.royale.shrink_every_n_turns = value.parse().map_err(invalid)?
+            }
+            "--seed" => self.seed = value.parse().map_err(invalid)?,
+            "--games" => self.games = value.parse().map_err(invalid)?,
+            "--max-turns" => self.max_turns = value.parse().map_err(invalid)?,
+            "--log" => self.log = Some(value.into()),
+            _ => return Err(format!("unknown flag {}", flag)),
+        }
+
+        Ok(())
+    }
+}
+
+/// One line of the arena game log.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Record<'a> {
+    Turn {
+        game: &'a str,
+        turn: u32,
+        board: Board,
+        moves: BTreeMap<String, Movement>,
+    },
+    End {
+        game: &'a str,
+        turns: u32,
+        winner: Option<String>,
+        eliminated: &'a [Eliminated],
+    },
+}
+
+/// How a single arena game ended.
+pub struct Outcome {
+    pub id: String,
+    pub turns: u32,
+    /// Index into the strategies the game was played with
+    pub winner: Option<usize>,
+    pub eliminated: Vec<Eliminated>,
+}
+
+/// Plays one game between `strategies`, snake `i` is named after `names[i]`.
+pub fn play(
+    strategies: &[&dyn Strategy],
+    names: &[String],
+    options: &Options,
+    seed: u64,
+    mut log: Option<&mut dyn Write>,
+) -> std::io::Result<Outcome> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = Game::new(
+        format!("arena-{}", seed),
+        options.width,
+        options.height,
+        options.rules,
+        options.settings,
+        names,
+        &mut rng,
+    );
+    // Engine ids are `snake-<index>` in the order they were created
+    let index = |id: &str| id.trim_start_matches("snake-").parse::<usize>().unwrap();
+
+    while !game.is_over() && game.turn < options.max_turns {
+        let moves: Vec<_> = game
+            .snakes
+            .iter()
+            .map(|s| {
+                let turn = game.turn_for(&s.id).unwrap();
+                strategies[index(&s.id)].get_move(turn).movement
+            })
+            .collect();
+
+        if let Some(log) = log.as_mut() {
+            let record = Record::Turn {
+                game: &game.id,
+                turn: game.turn,
+                board: game.board(),
+                moves: game
+                    .snakes
+                    .iter()
+                    .map(|s| s.id.clone())
+                    .zip(moves.iter().copied())
+                    .collect(),
+            };
+            writeln!(log, "{}", serde_json::to_string(&record)?)?;
+        }
+
+        game.step(&moves, &mut rng);
+    }
+
+    let winner = game.winner().map(|s| index(&s.id));
+
+    if let Some(log) = log.as_mut() {
+        let record = Record::End {
+            game: &game.id,
+            turns: game.turn,
+            winner: game.winner().map(|s| s.id.clone()),
+            eliminated: &game.eliminated,
+        };
+        writeln!(log, "{}", serde_json::to_string(&record)?)?;
+    }
+
+    Ok(Outcome {
+        id: game.id,
+        turns: game.turn,
+        winner,
+        eliminated: game.eliminated,
+    })
+}
+
+pub fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let strategies = options
+        .snakes
+        .iter()
+        .map(|spec| strategy::from_spec(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut log = match &options.log {
+        Some(path) => Some(BufWriter::new(File::create(path)?)),
+        None => None,
+    };
+
+    for g in 0..options.games {
+        let seed = options.seed.wrapping_add(g as u64);
+        let players: Vec<_> = strategies.iter().map(|s| s.as_ref()).collect();
+        let outcome = play(
+            &players,
+            &options.snakes,
+            options,
+            seed,
+            log.as_mut().map(|l| l as &mut dyn Write),
+        )?;
+
+        let winner = match outcome.winner {
+            Some(i) => format!("{} (snake-{})", options.snakes[i], i),
+            None => "draw".to_string(),
+        };
+        println!(
+            "{} (seed {}): {} after {} turns",
+            outcome.id, seed, winner, outcome.turns
+        );
+        for eliminated in &outcome.eliminated {
+            println!(
+                "  {} ({}): {:?} on turn {}",
+                eliminated.snake.name, eliminated.snake.id, eliminated.cause, eliminated.turn
+            );
+        }
+    }
+
+    if let Some(log) = log.as_mut() {
+        log.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(snakes: &[&str]) -> Options {
+        Options {
+            snakes: snakes.iter().map(|s| s.to_string()).collect(),
+            width: 7,
+            height: 7,
+            max_turns: 50,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_flags() {
+        let args = [
+            "--snake",
+            "minimax",
+            "--snake",
+            "random",
+            "--ruleset",
+            "royale",
+        ];
+        let options = Options::parse(args.map(String::from).into_iter()).unwrap();
+
+        assert_eq!(options.snakes, ["minimax", "random"]);
+        assert_eq!(options.rules, Rules::Royale);
+        assert!(Options::parse(std::iter::empty()).is_err());
+        assert!(Options::parse(["--ruleset", "wrapped"].map(String::from).into_iter()).is_err());
+
+        let parse = |args: &[&str]| Options::parse(args.iter().map(|a| a.to_string()));
+        assert!(parse(&["--snake", "random", "--width", "0"]).is_err());
+        assert!(parse(&["--snake", "random", "--width", "2", "--height", "2"]).is_err());
+        assert!(parse(&["--snake", "random", "--width", "5", "--height", "5"]).is_ok());
+        assert!(parse(&["--snake", "random"].repeat(9)).is_err());
+        assert!(parse(&["--snake", "random"].repeat(8)).is_ok());
+    }
+
+    #[test]
+    fn games_are_reproducible() {
+        let options = options(&["random", "random"]);
+        let strategies: Vec<_> = options
+            .snakes
+            .iter()
+            .map(|s| strategy::from_spec(s).unwrap())
+            .collect();
+        let strategies: Vec<_> = strategies.iter().map(|s| s.as_ref()).collect();
+
+        let mut first = Vec::new();
+        let mut second = Vec::new();
+        play(&strategies, &options.snakes, &options, 5, Some(&mut first)).unwrap();
+        play(&strategies, &options.snakes, &options, 5, Some(&mut second)).unwrap();
+
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+
+        let last = String::from_utf8(first).unwrap();
+        let last = last.lines().last().unwrap();
+        assert!(last.starts_with(r#"{"type":"end","game":"arena-5""#));
+    }
+}
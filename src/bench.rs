@@ -0,0 +1,217 @@
+use std::{
+    error::Error,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    logic::{self, Weights},
+    parse::parse,
+    requests::Turn,
+};
+
+// Searched when no turn files are given: open board, crowded middle game, four snakes
+const POSITIONS: &[(&str, &str)] = &[
+    (
+        "opening",
+        "
+        ...........
+        ...........
+        ...*.......
+        .....B.....
+        .....b.....
+        ...........
+        .....a.....
+        .....A.....
+        .......*...
+        ...........
+        ...........",
+    ),
+    (
+        "middle",
+        "
+        ...........
+        ..bbbbB....
+        ..b........
+        ..b..*.....
+        ..b........
+        ..bb.aaaa..
+        ...b.a..a..
+        ...b.A..a..
+        ...b....a..
+        ...b..aaa..
+        ...........",
+    ),
+    (
+        "four",
+        "
+        ...........
+        .A.......B.
+        .a.......b.
+        .a.......b.
+        ...........
+        .....*.....
+        ...........
+        .c.......d.
+        .c.......d.
+        .C.......D.
+        ...........",
+    ),
+];
+
+pub struct Options {
+    /// `/move` request bodies, the built-in positions when empty
+    pub turns: Vec<PathBuf>,
+    /// Searches per position, the fastest counts
+    pub runs: usize,
+    pub weights: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            turns: Vec::new(),
+            runs: 3,
+            weights: None,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--runs" => {
+                    options.runs = args
+                        .next()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|&runs| runs > 0)
+                        .ok_or("--runs takes a positive number")?
+                }
+                "--weights" => {
+                    options.weights = Some(args.next().ok_or("missing value for --weights")?.into())
+                }
+                _ if !arg.starts_with("--") => options.turns.push(arg.into()),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// How fast the search went on one position.
+pub struct Measurement {
+    pub name: String,
+    pub depth: u8,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl Measurement {
+    pub fn nodes_per_second(&self) -> f64 {
+        self.nodes as f64 / self.time.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Searches every position `runs` times, keeping the fastest run of each.
+pub fn bench(positions: &[(String, Turn)], weights: Weights, runs: usize) -> Vec<Measurement> {
+    positions
+        .iter()
+        .map(|(name, turn)| {
+            let mut fastest: Option<Measurement> = None;
+            for _ in 0..runs.max(1) {
+                let started = Instant::now();
+                let search = logic::search(turn.clone(), weights);
+                let time = started.elapsed();
+
+                let faster = match &fastest {
+                    Some(f) => time < f.time,
+                    None => true,
+                };
+                if faster {
+                    fastest = Some(Measurement {
+                        name: name.clone(),
+                        depth: search.depth,
+                        nodes: search.stats.nodes,
+                        time,
+                    });
+                }
+            }
+            fastest.unwrap()
+        })
+        .collect()
+}
+
+pub fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let weights = match &options.weights {
+        Some(path) => Weights::load(path)
+            .map_err(|e| format!("failed to load weights {}: {}", path.display(), e))?,
+        None => Weights::default(),
+    };
+
+    let positions = if options.turns.is_empty() {
+        POSITIONS
+            .iter()
+            .map(|(name, board)| Ok((name.to_string(), parse(board)?)))
+            .collect::<Result<Vec<_>, String>>()?
+    } else {
+        options
+            .turns
+            .iter()
+            .map(|path| {
+                let turn = fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+                    .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+                Ok((path.display().to_string(), turn))
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?
+    };
+
+    let results = bench(&positions, weights, options.runs);
+
+    println!(
+        "{:<20} {:>5} {:>12} {:>12} {:>12}",
+        "position", "depth", "nodes", "time", "nodes/s"
+    );
+    for result in &results {
+        println!(
+            "{:<20} {:>5} {:>12} {:>12} {:>12.0}",
+            result.name,
+            result.depth,
+            result.nodes,
+            format!("{:.1?}", result.time),
+            result.nodes_per_second()
+        );
+    }
+
+    let nodes: u64 = results.iter().map(|r| r.nodes).sum();
+    let time: Duration = results.iter().map(|r| r.time).sum();
+    println!(
+        "{:<20} {:>5} {:>12} {:>12} {:>12.0}",
+        "total",
+        "",
+        nodes,
+        format!("{:.1?}", time),
+        nodes as f64 / time.as_secs_f64().max(f64::EPSILON)
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_positions() {
+        let positions: Vec<_> = POSITIONS
+            .iter()
+            .map(|(name, board)| (name.to_string(), parse(board).unwrap()))
+            .collect();
+
+        let results = bench(&positions[..1], Weights::default(), 2);
+
+        assert_eq!(positions.len(), 3);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].nodes > 0);
+    }
+}
@@ -0,0 +1,377 @@
+//! Settings for `serve`. Defaults are overridden by the JSON file given with `--config`,
+//! then by the `SNEK_*` environment variables, then by flags.
+
+use std::{fs, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::{
+    deadline::Margins,
+    logic::{TieBreak, Weights},
+    responses::{HeadType, TailType},
+    shout::Shouts,
+    strategy,
+};
+
+#[derive(Deserialize, PartialEq, Debug, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Address to listen on
+    pub bind: String,
+    /// Strategy answering moves, as taken by the arena. `minimax` searches until the
+    /// deadline, anything else answers right away.
+    pub strategy: String,
+    /// Evaluation weights for `minimax`, reloaded between games
+    pub weights: Option<PathBuf>,
+    pub appearance: Appearance,
+    pub shouts: Shouts,
+    pub timing: Margins,
+    /// Where games are logged, not at all when unset
+    pub log_dir: Option<PathBuf>,
+    /// Newest games kept in `log_dir`
+    pub log_keep: usize,
+    /// Per-strategy results, not kept when unset
+    pub stats: Option<PathBuf>,
+    pub threads: Threads,
+    /// How root moves the search scored the same are told apart
+    pub tie_break: TieBreak,
+    /// Seeds every search instead of the game id, to reproduce games bit for bit
+    pub seed: Option<u64>,
+}
+
+/// How the snake looks on the board, as reported on `/`.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Appearance {
+    pub author: Option<String>,
+    /// `#rrggbb`
+    pub color: String,
+    /// The engine's own when unset
+    pub head: Option<HeadType>,
+    pub tail: Option<TailType>,
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Threads {
+    /// Searches running at once
+    pub search: usize,
+    /// Searches running at once for any one game
+    pub per_game: usize,
+    /// Threads each search splits its root moves across, 1 searches them in order. Up to
+    /// `search` times this many threads are busy at once.
+    pub root: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:3000".to_string(),
+            strategy: "minimax".to_string(),
+            weights: None,
+            appearance: Appearance::default(),
+            shouts: Shouts::default(),
+            timing: Margins::default(),
+            log_dir: None,
+            log_keep: 100,
+            stats: None,
+            threads: Threads::default(),
+            tie_break: TieBreak::default(),
+            seed: None,
+        }
+    }
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            author: None,
+            color: "#b7410e".to_string(),
+            head: None,
+            tail: None,
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }
+    }
+}
+
+impl Appearance {
+    /// Heads and tails are checked when parsed, the engine would ignore a bad color.
+    fn validate(&self) -> Result<(), String> {
+        let hex = self.color.strip_prefix('#').unwrap_or_default();
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("color is not #rrggbb: {}", self.color));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Threads {
+    fn default() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            search: cores,
+            per_game: 1,
+            // Each search already has a core of its own
+            root: 1,
+        }
+    }
+}
+
+/// Flags taken by `serve`.
+#[derive(Default)]
+pub struct Options {
+    pub config: Option<PathBuf>,
+    pub bind: Option<String>,
+    pub strategy: Option<String>,
+    pub weights: Option<PathBuf>,
+    pub log_dir: Option<PathBuf>,
+    pub stats: Option<PathBuf>,
+    pub seed: Option<u64>,
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", flag))?;
+            match flag.as_str() {
+                "--config" => options.config = Some(value.into()),
+                "--bind" => options.bind = Some(value),
+                "--strategy" => options.strategy = Some(value),
+                "--weights" => options.weights = Some(value.into()),
+                "--log-dir" => options.log_dir = Some(value.into()),
+                "--stats" => options.stats = Some(value.into()),
+                "--seed" => {
+                    options.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid seed {}", value))?,
+                    )
+                }
+                _ => return Err(format!("unknown flag {}", flag)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+impl Config {
+    /// Layers the config file, the environment and the flags in `options` over the defaults.
+    pub fn load(options: &Options) -> Result<Self, String> {
+        Self::load_with(options, |name| std::env::var(name).ok())
+    }
+
+    fn load_with(options: &Options, var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let mut config = match &options.config {
+            Some(path) => {
+                let json = fs::read_to_string(path)
+                    .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+                serde_json::from_str(&json)
+                    .map_err(|e| format!("invalid config {}: {}", path.display(), e))?
+            }
+            None => Self::default(),
+        };
+
+        config.apply_env(var)?;
+        config.apply(options);
+
+        // Fail now rather than on the first move
+        config.appearance.validate()?;
+        strategy::from_spec(&config.strategy)?;
+        // The server reloads minimax weights itself
+        if let Some(("minimax", path)) = config.strategy.split_once(':') {
+            config.weights = Some(path.into());
+            config.strategy = "minimax".to_string();
+        }
+        if let Some(path) = &config.weights {
+            Weights::load(path)
+                .map_err(|e| format!("failed to load weights {}: {}", path.display(), e))?;
+        }
+
+        Ok(config)
+    }
+
+    // An empty SNEK_LOG_DIR or SNEK_STATS turns those off
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let path = |value: String| (!value.is_empty()).then(|| value.into());
+        let number = |name: &str, value: String| {
+            value
+                .parse()
+                .map_err(|_| format!("{} is not a number: {}", name, value))
+        };
+
+        if let Some(bind) = var("SNEK") {
+            self.bind = bind;
+        }
+        if let Some(weights) = var("SNEK_WEIGHTS") {
+            self.weights = path(weights);
+        }
+        if let Some(dir) = var("SNEK_LOG_DIR") {
+            self.log_dir = path(dir);
+        }
+        if let Some(keep) = var("SNEK_LOG_KEEP") {
+            self.log_keep = number("SNEK_LOG_KEEP", keep)?;
+        }
+        if let Some(stats) = var("SNEK_STATS") {
+            self.stats = path(stats);
+        }
+        if let Some(threads) = var("SNEK_SEARCH_THREADS") {
+            self.threads.search = number("SNEK_SEARCH_THREADS", threads)?;
+        }
+        if let Some(threads) = var("SNEK_GAME_SEARCHES") {
+            self.threads.per_game = number("SNEK_GAME_SEARCHES", threads)?;
+        }
+        if let Some(threads) = var("SNEK_ROOT_THREADS") {
+            self.threads.root = number("SNEK_ROOT_THREADS", threads)?;
+        }
+        if let Some(seed) = var("SNEK_SEED") {
+            self.seed = Some(
+                seed.parse()
+                    .map_err(|_| format!("SNEK_SEED is not a number: {}", seed))?,
+            );
+        }
+        if let Some(shouts) = var("SNEK_SHOUTS") {
+            self.shouts.enabled = shouts
+                .parse()
+                .map_err(|_| format!("SNEK_SHOUTS is not true or false: {}", shouts))?;
+        }
+
+        Ok(())
+    }
+
+    fn apply(&mut self, options: &Options) {
+        if let Some(bind) = &options.bind {
+            self.bind = bind.clone();
+        }
+        if let Some(strategy) = &options.strategy {
+            self.strategy = strategy.clone();
+        }
+        if let Some(weights) = &options.weights {
+            self.weights = Some(weights.clone());
+        }
+        if let Some(dir) = &options.log_dir {
+            self.log_dir = Some(dir.clone());
+        }
+        if let Some(stats) = &options.stats {
+            self.stats = Some(stats.clone());
+        }
+        if let Some(seed) = options.seed {
+            self.seed = Some(seed);
+        }
+    }
+
+    /// The strategy spec results are recorded under, with the weights file if any.
+    pub fn strategy_spec(&self) -> String {
+        match (&self.weights, self.strategy.as_str()) {
+            (Some(path), "minimax") => format!("minimax:{}", path.display()),
+            _ => self.strategy.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn file_then_env_then_flags() {
+        let mut config: Config = serde_json::from_str(
+            r##"{
+                "bind": "0.0.0.0:8000",
+                "weights": "file.json",
+                "appearance": { "color": "#112233", "head": "smile" },
+                "shouts": { "every": 3 },
+                "timing": { "margin-ms": 5 },
+                "log-dir": "games",
+                "stats": "stats.json",
+                "threads": { "per-game": 2 },
+                "tie-break": "centre"
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(config.appearance.color, "#112233");
+        assert_eq!(config.appearance.head, Some(HeadType::Smile));
+        assert_eq!(config.appearance.tail, None);
+        assert_eq!(config.shouts.every, 3);
+        assert!(config.shouts.enabled);
+        assert_eq!(config.timing.margin_ms, 5);
+        assert_eq!(config.timing.overhead_ms, Margins::default().overhead_ms);
+        assert_eq!(config.log_dir, Some("games".into()));
+        assert_eq!(config.threads.per_game, 2);
+        assert_eq!(config.tie_break, TieBreak::Centre);
+
+        let env = HashMap::from([
+            ("SNEK", "0.0.0.0:9000"),
+            ("SNEK_STATS", ""),
+            ("SNEK_LOG_KEEP", "5"),
+            ("SNEK_SHOUTS", "false"),
+        ]);
+        config
+            .apply_env(|name| env.get(name).map(|v| v.to_string()))
+            .unwrap();
+        assert_eq!(config.bind, "0.0.0.0:9000");
+        assert_eq!(config.stats, None);
+        assert_eq!(config.log_keep, 5);
+        assert!(!config.shouts.enabled);
+
+        let options = Options::parse(
+            [
+                "--bind",
+                "127.0.0.1:1234",
+                "--weights",
+                "flag.json",
+                "--seed",
+                "9",
+            ]
+            .into_iter()
+            .map(String::from),
+        )
+        .unwrap();
+        config.apply(&options);
+        assert_eq!(config.bind, "127.0.0.1:1234");
+        assert_eq!(config.strategy_spec(), "minimax:flag.json");
+        assert_eq!(config.seed, Some(9));
+    }
+
+    #[test]
+    fn rejects_mistakes() {
+        assert!(serde_json::from_str::<Config>(r#"{ "bnid": "0.0.0.0:1" }"#).is_err());
+        assert!(Config::default()
+            .apply_env(|name| (name == "SNEK_LOG_KEEP").then(|| "many".to_string()))
+            .is_err());
+        assert!(Options::parse(["--bind".to_string()].into_iter()).is_err());
+        assert!(Options::parse(["--color".to_string(), "red".to_string()].into_iter()).is_err());
+
+        assert!(serde_json::from_str::<Appearance>(r#"{ "head": "smiley" }"#).is_err());
+        let mut appearance: Appearance =
+            serde_json::from_str(r##"{ "head": "sand-worm", "tail": "regular" }"##).unwrap();
+        assert_eq!(appearance.tail, Some(TailType::Default));
+        appearance.validate().unwrap();
+        appearance.color = "#b7410".to_string();
+        assert!(appearance.validate().is_err());
+        appearance.color = "rust".to_string();
+        assert!(appearance.validate().is_err());
+
+        let options = Options {
+            strategy: Some("alphazero".to_string()),
+            ..Default::default()
+        };
+        assert!(Config::load_with(&options, |_| None).is_err());
+        let options = Options {
+            strategy: Some("minimax:does-not-exist.json".to_string()),
+            ..Default::default()
+        };
+        assert!(Config::load_with(&options, |_| None).is_err());
+        assert!(Config::load_with(&Options::default(), |name| {
+            (name == "SNEK_WEIGHTS").then(|| "does-not-exist.json".to_string())
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+// Recent round trips the overhead is estimated from
+const WINDOW: usize = 10;
+
+/// How much of the timeout is kept away from the search.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Margins {
+    /// Assumed network overhead until the engine has reported a latency for our answers
+    pub overhead_ms: u64,
+    /// Left between the search finishing and the response going out
+    pub margin_ms: u64,
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Self {
+            overhead_ms: 100,
+            margin_ms: 20,
+        }
+    }
+}
+
+/// Network overhead seen in one game, from the latency the engine reports for each of our
+/// answers minus the time we spent computing it.
+#[derive(Default, Debug)]
+pub struct Latency {
+    samples: VecDeque<Duration>,
+    answered: Option<Duration>,
+}
+
+impl Latency {
+    /// Takes the latency the engine reported for our previous answer, in milliseconds.
+    pub fn observe(&mut self, reported: u32) {
+        let Some(answered) = self.answered.take() else {
+            return;
+        };
+        // Zero until the engine has measured anything
+        if reported == 0 {
+            return;
+        }
+
+        let overhead = Duration::from_millis(reported as u64).saturating_sub(answered);
+        self.samples.push_back(overhead);
+        if self.samples.len() > WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Remembers how long we took to answer, to be matched with the next reported latency.
+    pub fn answered(&mut self, elapsed: Duration) {
+        self.answered = Some(elapsed);
+    }
+
+    /// The worst recent overhead, so a single slow round trip shrinks the budget at once.
+    pub fn overhead(&self, margins: &Margins) -> Duration {
+        self.samples
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or(Duration::from_millis(margins.overhead_ms))
+    }
+
+    /// When the search has to be done by for a request received at `received`.
+    pub fn deadline(&self, received: Instant, timeout_ms: i32, margins: &Margins) -> Instant {
+        let timeout = Duration::from_millis(timeout_ms.max(0) as u64);
+        let reserved = self.overhead(margins) + Duration::from_millis(margins.margin_ms);
+        received + timeout.saturating_sub(reserved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT_OVERHEAD: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn overhead_from_reported_latency() {
+        let margins = Margins::default();
+        let mut latency = Latency::default();
+        assert_eq!(latency.overhead(&margins), DEFAULT_OVERHEAD);
+
+        // Nothing answered yet, nothing to compare against
+        latency.observe(300);
+        assert_eq!(latency.overhead(&margins), DEFAULT_OVERHEAD);
+
+        latency.answered(Duration::from_millis(200));
+        latency.observe(0);
+        assert_eq!(latency.overhead(&margins), DEFAULT_OVERHEAD);
+
+        latency.answered(Duration::from_millis(200));
+        latency.observe(260);
+        latency.answered(Duration::from_millis(250));
+        latency.observe(290);
+        assert_eq!(latency.overhead(&margins), Duration::from_millis(60));
+
+        for _ in 0..WINDOW {
+            latency.answered(Duration::from_millis(100));
+            latency.observe(130);
+        }
+        assert_eq!(latency.overhead(&margins), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn deadline_leaves_room_for_overhead() {
+        let mut latency = Latency::default();
+        latency.answered(Duration::from_millis(100));
+        latency.observe(180);
+
+        let margins = Margins::default();
+        let now = Instant::now();
+        assert_eq!(
+            latency.deadline(now, 500, &margins),
+            now + Duration::from_millis(500 - 80 - 20)
+        );
+        assert_eq!(latency.deadline(now, 50, &margins), now);
+
+        let tight = Margins {
+            margin_ms: 5,
+            ..margins
+        };
+        assert_eq!(
+            latency.deadline(now, 500, &tight),
+            now + Duration::from_millis(500 - 80 - 5)
+        );
+    }
+}
@@ -0,0 +1,602 @@
+use std::str::FromStr;
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    requests::{Board, Game as GameInfo, Point, Ruleset, RulesetSettings, Snake, Turn},
+    responses::Movement,
+};
+
+const MAX_HEALTH: i32 = 100;
+const START_LENGTH: usize = 3;
+/// Number of standard start positions, and so the most snakes a game can hold.
+pub const MAX_SNAKES: usize = 8;
+/// Smallest side that keeps the start positions apart and their food on the board.
+pub const MIN_SIZE: i32 = 5;
+
+/// Checks that `snakes` snakes fit a `width` by `height` board before a game is started.
+pub fn check_board(width: i32, height: i32, snakes: usize) -> Result<(), String> {
+    if width < MIN_SIZE || height < MIN_SIZE {
+        return Err(format!(
+            "board must be at least {}x{}, got {}x{}",
+            MIN_SIZE, MIN_SIZE, width, height
+        ));
+    }
+    if snakes > MAX_SNAKES {
+        return Err(format!("at most {} snakes, got {}", MAX_SNAKES, snakes));
+    }
+    Ok(())
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Rules {
+    Standard,
+    /// Hazards close in from a random side every `shrinkEveryNTurns`
+    Royale,
+    /// No food, every snake grows and is fed every turn
+    Constrictor,
+}
+
+impl Rules {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rules::Standard => "standard",
+            Rules::Royale => "royale",
+            Rules::Constrictor => "constrictor",
+        }
+    }
+}
+
+impl FromStr for Rules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Rules::Standard),
+            "royale" => Ok(Rules::Royale),
+            "constrictor" => Ok(Rules::Constrictor),
+            _ => Err(format!(
+                "unknown ruleset {}, expected standard, royale or constrictor",
+                s
+            )),
+        }
+    }
+}
+
+/// Why a snake left the game.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cause {
+    Wall,
+    SelfCollision,
+    BodyCollision,
+    HeadToHead,
+    Starvation,
+    Hazard,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Eliminated {
+    pub snake: Snake,
+    pub cause: Cause,
+    pub turn: u32,
+}
+
+/// A game played by the local rules engine, following the standard ruleset.
+pub struct Game {
+    pub id: String,
+    pub turn: u32,
+    pub width: i32,
+    pub height: i32,
+    pub rules: Rules,
+    pub settings: RulesetSettings,
+    pub food: Vec<Point>,
+    pub hazards: Vec<Point>,
+    pub snakes: Vec<Snake>,
+    pub eliminated: Vec<Eliminated>,
+    // Royale safe zone as [left, right, bottom, top], inclusive
+    safe_zone: [i32; 4],
+}
+
+impl Game {
+    /// Places one snake per name on the standard start positions, with food next to each.
+    pub fn new(
+        id: String,
+        width: i32,
+        height: i32,
+        rules: Rules,
+        settings: RulesetSettings,
+        names: &[String],
+        rng: &mut impl Rng,
+    ) -> Self {
+        let (low, mid, high) = (1, (width - 1) / 2, width - 2);
+        let (bottom, centre, top) = (1, (height - 1) / 2, height - 2);
+        let mut starts = vec![
+            Point { x: low, y: bottom },
+            Point { x: low, y: top },
+            Point { x: high, y: bottom },
+            Point { x: high, y: top },
+            Point { x: mid, y: bottom },
+            Point { x: low, y: centre },
+            Point { x: high, y: centre },
+            Point { x: mid, y: top },
+        ];
+        if let Err(e) = check_board(width, height, names.len()) {
+            panic!("{}", e);
+        }
+
+        // Corners first, shuffled within each group so seeds vary the layout
+        starts[..4].shuffle(rng);
+        starts[4..].shuffle(rng);
+
+        let snakes: Vec<_> = names
+            .iter()
+            .zip(&starts)
+            .enumerate()
+            .map(|(i, (name, &start))| Snake {
+                id: format!("snake-{}", i),
+                name: name.clone(),
+                health: MAX_HEALTH,
+                body: vec![start; START_LENGTH],
+                head: start,
+                length: START_LENGTH as u32,
+                shout: String::new(),
+                squad: String::new(),
+                latency: 0,
+            })
+            .collect();
+
+        let mut game = Self {
+            id,
+            turn: 0,
+            width,
+            height,
+            rules,
+            settings,
+            food: Vec::new(),
+            hazards: Vec::new(),
+            snakes,
+            eliminated: Vec::new(),
+            safe_zone: [0, width - 1, 0, height - 1],
+        };
+
+        if rules == Rules::Constrictor {
+            return game;
+        }
+
+        let centre = Point { x: mid, y: centre };
+        for head in game.snakes.iter().map(|s| s.head).collect::<Vec<_>>() {
+            let diagonals = [
+                Point {
+                    x: head.x - 1,
+                    y: head.y - 1,
+                },
+                Point {
+                    x: head.x - 1,
+                    y: head.y + 1,
+                },
+                Point {
+                    x: head.x + 1,
+                    y: head.y - 1,
+                },
+                Point {
+                    x: head.x + 1,
+                    y: head.y + 1,
+                },
+            ];
+            let free: Vec<_> = diagonals
+                .into_iter()
+                .filter(|&p| p != centre && game.is_free(p))
+                .collect();
+            if let Some(&food) = free.choose(rng) {
+                game.food.push(food);
+            }
+        }
+        if game.is_free(centre) {
+            game.food.push(centre);
+        }
+
+        game
+    }
+
+    pub fn board(&self) -> Board {
+        Board {
+            height: self.height,
+            width: self.width,
+            food: self.food.clone(),
+            snakes: self.snakes.clone(),
+            hazards: self.hazards.clone(),
+        }
+    }
+
+    /// The request the engine would send to the snake with `id`.
+    pub fn turn_for(&self, id: &str) -> Option<Turn> {
+        let you = self.snakes.iter().find(|s| s.id == id)?.clone();
+
+        Some(Turn {
+            game: GameInfo {
+                id: self.id.clone(),
+                ruleset: Ruleset {
+                    name: self.rules.name().to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    settings: self.settings,
+                },
+                timeout: 500,
+            },
+            turn: self.turn,
+            board: self.board(),
+            you,
+        })
+    }
+
+    /// Game over once at most one snake is left, or none in a solo game.
+    pub fn is_over(&self) -> bool {
+        let started = self.snakes.len() + self.eliminated.len();
+        if started == 1 {
+            self.snakes.is_empty()
+        } else {
+            self.snakes.len() <= 1
+        }
+    }
+
+    pub fn winner(&self) -> Option<&Snake> {
+        match self.snakes.as_slice() {
+            [winner] if self.is_over() => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// Advances one turn, `moves` lines up with `self.snakes`.
+    pub fn step(&mut self, moves: &[Movement], rng: &mut impl Rng) {
+        assert_eq!(moves.len(), self.snakes.len());
+
+        for (snake, &movement) in self.snakes.iter_mut().zip(moves) {
+            let head = next_head(snake.head, movement);
+            snake.body.insert(0, head);
+            snake.body.pop();
+            snake.head = head;
+            snake.health -= 1;
+
+            if self.hazards.contains(&head) && !self.food.contains(&head) {
+                snake.health -= self.settings.hazard_damage_per_turn as i32;
+            }
+        }
+
+        for snake in &mut self.snakes {
+            if self.rules == Rules::Constrictor || self.food.contains(&snake.head) {
+                snake.health = MAX_HEALTH;
+                snake.body.push(*snake.body.last().unwrap());
+                snake.length = snake.body.len() as u32;
+            }
+        }
+        let heads: Vec<_> = self.snakes.iter().map(|s| s.head).collect();
+        self.food.retain(|f| !heads.contains(f));
+
+        self.eliminate();
+        self.turn += 1;
+
+        match self.rules {
+            Rules::Standard => self.spawn_food(rng),
+            Rules::Royale => {
+                self.spawn_food(rng);
+                let every = self.settings.royale.shrink_every_n_turns;
+                if every > 0 && self.turn % every == 0 {
+                    self.shrink(rng);
+                }
+            }
+            Rules::Constrictor => {}
+        }
+    }
+
+    fn shrink(&mut self, rng: &mut impl Rng) {
+        let [left, right, bottom, top] = &mut self.safe_zone;
+        if left > right || bottom > top {
+            return;
+        }
+
+        match rng.gen_range(0..4) {
+            0 => *left += 1,
+            1 => *right -= 1,
+            2 => *bottom += 1,
+            _ => *top -= 1,
+        }
+
+        let [left, right, bottom, top] = self.safe_zone;
+        self.hazards = (0..self.width)
+            .flat_map(|x| (0..self.height).map(move |y| Point { x, y }))
+            .filter(|p| p.x < left || p.x > right || p.y < bottom || p.y > top)
+            .collect();
+    }
+
+    fn spawn_food(&mut self, rng: &mut impl Rng) {
+        let minimum = self.settings.minimum_food as usize;
+        let needed = if self.food.len() < minimum {
+            minimum - self.food.len()
+        } else if rng.gen_range(0..100) < self.settings.food_spawn_chance {
+            1
+        } else {
+            0
+        };
+
+        if needed == 0 {
+            return;
+        }
+
+        let free: Vec<_> = (0..self.width)
+            .flat_map(|x| (0..self.height).map(move |y| Point { x, y }))
+            .filter(|&p| self.is_free(p))
+            .collect();
+
+        self.food.extend(free.choose_multiple(rng, needed).copied());
+    }
+
+    fn is_free(&self, p: Point) -> bool {
+        !self.food.contains(&p) && !self.snakes.iter().any(|s| s.body.contains(&p))
+    }
+
+    fn eliminate(&mut self) {
+        let causes: Vec<_> = (0..self.snakes.len())
+            .map(|i| self.cause_of_death(i))
+            .collect();
+
+        let mut i = 0;
+        for cause in causes {
+            match cause {
+                Some(cause) => self.eliminated.push(Eliminated {
+                    snake: self.snakes.remove(i),
+                    cause,
+                    turn: self.turn + 1,
+                }),
+                None => i += 1,
+            }
+        }
+    }
+
+    fn cause_of_death(&self, index: usize) -> Option<Cause> {
+        let snake = &self.snakes[index];
+        let head = snake.head;
+
+        if snake.health <= 0 {
+            return Some(if self.hazards.contains(&head) {
+                Cause::Hazard
+            } else {
+                Cause::Starvation
+            });
+        }
+
+        if head.x < 0 || head.x >= self.width || head.y < 0 || head.y >= self.height {
+            return Some(Cause::Wall);
+        }
+
+        if snake.body[1..].contains(&head) {
+            return Some(Cause::SelfCollision);
+        }
+
+        let others = self
+            .snakes
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .map(|(_, s)| s);
+
+        for other in others.clone() {
+            if other.body[1..].contains(&head) {
+                return Some(Cause::BodyCollision);
+            }
+        }
+
+        for other in others {
+            if other.head == head && other.body.len() >= snake.body.len() {
+                return Some(Cause::HeadToHead);
+            }
+        }
+
+        None
+    }
+}
+
+pub fn next_head(p: Point, movement: Movement) -> Point {
+    match movement {
+        Movement::Right => Point { x: p.x + 1, ..p },
+        Movement::Left => Point { x: p.x - 1, ..p },
+        Movement::Up => Point { y: p.y + 1, ..p },
+        Movement::Down => Point { y: p.y - 1, ..p },
+    }
+}
+
+/// Plays `game` to the end, asking `get_move` for every snake each turn.
+pub fn run(
+    game: &mut Game,
+    max_turns: u32,
+    rng: &mut impl Rng,
+    mut get_move: impl FnMut(Turn) -> Movement,
+) {
+    while !game.is_over() && game.turn < max_turns {
+        let moves: Vec<_> = game
+            .snakes
+            .iter()
+            .map(|s| get_move(game.turn_for(&s.id).unwrap()))
+            .collect();
+
+        game.step(&moves, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn game(names: &[&str]) -> (Game, StdRng) {
+        let mut rng = StdRng::seed_from_u64(3);
+        let names: Vec<_> = names.iter().map(|n| n.to_string()).collect();
+        let game = Game::new(
+            "test".to_string(),
+            11,
+            11,
+            Rules::Standard,
+            RulesetSettings::default(),
+            &names,
+            &mut rng,
+        );
+        (game, rng)
+    }
+
+    fn place(game: &mut Game, index: usize, body: Vec<Point>) {
+        let snake = &mut game.snakes[index];
+        snake.head = body[0];
+        snake.length = body.len() as u32;
+        snake.body = body;
+    }
+
+    #[test]
+    fn setup() {
+        let (game, _) = game(&["a", "b"]);
+
+        assert_eq!(game.snakes.len(), 2);
+        assert!(game.snakes.iter().all(|s| s.body.len() == 3));
+        assert_eq!(game.food.len(), 3);
+        assert!(game.food.contains(&Point { x: 5, y: 5 }));
+    }
+
+    #[test]
+    fn eat_and_grow() {
+        let (mut game, mut rng) = game(&["a"]);
+        game.food = vec![Point { x: 2, y: 1 }];
+        game.snakes[0].health = 10;
+        place(&mut game, 0, vec![Point { x: 1, y: 1 }; 3]);
+
+        game.step(&[Movement::Right], &mut rng);
+
+        let snake = &game.snakes[0];
+        assert_eq!(snake.health, MAX_HEALTH);
+        assert_eq!(snake.body.len(), 4);
+        assert!(!game.food.contains(&Point { x: 2, y: 1 }));
+    }
+
+    #[test]
+    fn wall_and_starvation() {
+        let (mut game, mut rng) = game(&["a", "b"]);
+        place(&mut game, 0, vec![Point { x: 0, y: 1 }; 3]);
+        game.snakes[1].health = 1;
+
+        game.step(&[Movement::Left, Movement::Up], &mut rng);
+
+        assert!(game.is_over());
+        assert_eq!(game.winner(), None);
+        assert_eq!(game.eliminated[0].cause, Cause::Wall);
+        assert_eq!(game.eliminated[1].cause, Cause::Starvation);
+    }
+
+    #[test]
+    fn head_to_head_favours_longer_snake() {
+        let (mut game, mut rng) = game(&["a", "b"]);
+        game.food.clear();
+        place(
+            &mut game,
+            0,
+            vec![
+                Point { x: 3, y: 5 },
+                Point { x: 2, y: 5 },
+                Point { x: 1, y: 5 },
+                Point { x: 0, y: 5 },
+            ],
+        );
+        place(
+            &mut game,
+            1,
+            vec![
+                Point { x: 5, y: 5 },
+                Point { x: 6, y: 5 },
+                Point { x: 7, y: 5 },
+            ],
+        );
+
+        game.step(&[Movement::Right, Movement::Left], &mut rng);
+
+        assert_eq!(game.winner().map(|s| s.name.as_str()), Some("a"));
+        assert_eq!(game.eliminated[0].cause, Cause::HeadToHead);
+    }
+
+    #[test]
+    fn body_collision() {
+        let (mut game, mut rng) = game(&["a", "b"]);
+        game.food.clear();
+        place(&mut game, 0, vec![Point { x: 4, y: 4 }; 3]);
+        place(
+            &mut game,
+            1,
+            vec![
+                Point { x: 5, y: 6 },
+                Point { x: 5, y: 5 },
+                Point { x: 5, y: 4 },
+                Point { x: 5, y: 3 },
+            ],
+        );
+
+        game.step(&[Movement::Right, Movement::Up], &mut rng);
+
+        assert_eq!(game.eliminated[0].cause, Cause::BodyCollision);
+        assert_eq!(game.eliminated[0].snake.name, "a");
+    }
+
+    #[test]
+    fn royale_shrinks() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let settings = RulesetSettings {
+            minimum_food: 0,
+            food_spawn_chance: 0,
+            ..Default::default()
+        };
+        let mut game = Game::new(
+            "test".to_string(),
+            7,
+            7,
+            Rules::Royale,
+            settings,
+            &["a".to_string()],
+            &mut rng,
+        );
+        game.food.clear();
+        place(&mut game, 0, vec![Point { x: 3, y: 3 }; 3]);
+
+        // Chase our tail around a 2x2 square until the first shrink
+        let square = [
+            Movement::Up,
+            Movement::Right,
+            Movement::Down,
+            Movement::Left,
+        ];
+        for turn in 0..settings.royale.shrink_every_n_turns {
+            assert!(game.hazards.is_empty());
+            game.step(&[square[turn as usize % 4]], &mut rng);
+        }
+
+        assert_eq!(game.hazards.len(), 7);
+    }
+
+    #[test]
+    fn constrictor_grows_every_turn() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut game = Game::new(
+            "test".to_string(),
+            11,
+            11,
+            Rules::Constrictor,
+            RulesetSettings::default(),
+            &["a".to_string()],
+            &mut rng,
+        );
+        assert!(game.food.is_empty());
+        place(&mut game, 0, vec![Point { x: 1, y: 1 }; 3]);
+
+        game.step(&[Movement::Up], &mut rng);
+        game.step(&[Movement::Up], &mut rng);
+
+        assert_eq!(game.snakes[0].body.len(), 5);
+        assert_eq!(game.snakes[0].health, MAX_HEALTH);
+        assert!(game.food.is_empty());
+    }
+}
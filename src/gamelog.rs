@@ -0,0 +1,218 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    logic::{Candidate, Stats, TieBreak},
+    requests::Turn,
+    responses::Movement,
+};
+
+/// One line of a game log, every request we received and what we answered.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Entry {
+    Start {
+        request: Turn,
+        /// What the searches were seeded with instead of the game id, if anything
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
+        /// How the searches broke ties, replays have to break them the same way
+        #[serde(default)]
+        tie_break: TieBreak,
+    },
+    Move {
+        request: Turn,
+        chosen: Movement,
+        candidates: Vec<Candidate>,
+        /// Deepest iteration the search finished, replays search this deep
+        depth: u8,
+        #[serde(default)]
+        stats: Stats,
+        latency_us: u64,
+    },
+    End {
+        request: Turn,
+    },
+}
+
+impl Entry {
+    pub fn request(&self) -> &Turn {
+        match self {
+            Entry::Start { request, .. } | Entry::Move { request, .. } | Entry::End { request } => {
+                request
+            }
+        }
+    }
+}
+
+/// Appends entries to one JSONL file per game and snake, keeping the newest `keep` files.
+pub struct GameLog {
+    dir: PathBuf,
+    keep: usize,
+}
+
+impl GameLog {
+    pub fn new(dir: PathBuf, keep: usize) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, keep })
+    }
+
+    pub fn path(&self, turn: &Turn) -> PathBuf {
+        // Ids come from the engine, keep them from escaping the directory
+        let name: String = format!("{}_{}", turn.game.id, turn.you.id)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        self.dir.join(name).with_extension("jsonl")
+    }
+
+    pub fn append(&self, entry: &Entry) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(entry.request()))?;
+
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+    }
+
+    /// Deletes the oldest logs beyond `keep`.
+    pub fn rotate(&self) -> io::Result<()> {
+        let mut logs = Vec::new();
+        for file in fs::read_dir(&self.dir)? {
+            let file = file?;
+            let path = file.path();
+            if path.extension().is_some_and(|e| e == "jsonl") {
+                logs.push((file.metadata()?.modified()?, path));
+            }
+        }
+
+        if logs.len() <= self.keep {
+            return Ok(());
+        }
+
+        logs.sort();
+        let excess = logs.len() - self.keep;
+        for (_, path) in logs.into_iter().take(excess) {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads back every entry of a game log.
+pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).map_err(io::Error::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        engine::{Game, Rules},
+        logic::Ply,
+        requests::RulesetSettings,
+    };
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("battle_mulle-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn turn(id: &str) -> Turn {
+        let mut rng = StdRng::seed_from_u64(7);
+        let names = ["us".to_string(), "them".to_string()];
+        let game = Game::new(
+            id.to_string(),
+            11,
+            11,
+            Rules::Standard,
+            RulesetSettings::default(),
+            &names,
+            &mut rng,
+        );
+        game.turn_for("snake-0").unwrap()
+    }
+
+    #[test]
+    fn append_and_read_back() {
+        let log = GameLog::new(dir("append"), 10).unwrap();
+        let request = turn("game/../1");
+
+        let entries = vec![
+            Entry::Start {
+                request: request.clone(),
+                seed: Some(9),
+                tie_break: TieBreak::Random,
+            },
+            Entry::Move {
+                request: request.clone(),
+                chosen: Movement::Up,
+                candidates: vec![Candidate {
+                    movement: Movement::Up,
+                    score: 3,
+                    pv: vec![Ply::You(Movement::Up), Ply::Others(vec![Movement::Left])],
+                }],
+                depth: 7,
+                stats: Stats {
+                    nodes: 1000,
+                    expanded: 400,
+                    ..Default::default()
+                },
+                latency_us: 1200,
+            },
+            Entry::End {
+                request: request.clone(),
+            },
+        ];
+        for entry in &entries {
+            log.append(entry).unwrap();
+        }
+
+        let path = log.path(&request);
+        assert_eq!(path.parent(), Some(log.dir.as_path()));
+        assert_eq!(read(&path).unwrap(), entries);
+    }
+
+    #[test]
+    fn rotation_keeps_newest() {
+        let log = GameLog::new(dir("rotate"), 2).unwrap();
+
+        for id in ["a", "b", "c"] {
+            log.append(&Entry::Start {
+                request: turn(id),
+                seed: None,
+                tie_break: TieBreak::default(),
+            })
+            .unwrap();
+            // Make sure modification times differ
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        log.rotate().unwrap();
+
+        assert!(!log.path(&turn("a")).exists());
+        assert!(log.path(&turn("b")).exists());
+        assert!(log.path(&turn("c")).exists());
+    }
+}
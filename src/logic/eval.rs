@@ -0,0 +1,309 @@
+use std::{collections::VecDeque, path::PathBuf, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::requests::Point;
+
+use super::State;
+
+/// Weights for each evaluation term, loaded from a JSON file.
+///
+/// Missing fields keep their default, so a file only needs the weights it changes.
+/// The defaults reproduce the original hardcoded scoring.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone)]
+#[serde(default)]
+pub struct Weights {
+    /// Per food we eat along the line
+    pub length: isize,
+    /// Per food an enemy eats along the line
+    pub enemy_length: isize,
+    /// Our health at the leaf
+    pub health: isize,
+    /// Cells reachable from our head
+    pub space: isize,
+    /// Cells we reach before any enemy
+    pub territory: isize,
+    /// Distance to the closest food
+    pub food_distance: isize,
+    /// Our head standing in a hazard
+    pub hazard: isize,
+    /// Closeness to the nearest shorter enemy head
+    pub aggression: isize,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            length: 100,
+            enemy_length: -10,
+            health: 0,
+            space: 0,
+            territory: 0,
+            food_distance: 1,
+            hazard: 0,
+            aggression: 0,
+        }
+    }
+}
+
+impl Weights {
+    pub fn load(path: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&file)?)
+    }
+}
+
+/// Weights backed by an optional file, reloaded whenever the file changes on disk.
+pub struct WeightsFile {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    weights: Weights,
+}
+
+impl WeightsFile {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let mut file = Self {
+            path,
+            modified: None,
+            weights: Weights::default(),
+        };
+        file.reload();
+        file
+    }
+
+    pub fn weights(&self) -> Weights {
+        self.weights
+    }
+
+    /// Picks up changes to the file since the last reload.
+    pub fn reload(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified == self.modified {
+            return;
+        }
+
+        // A broken file keeps the previous weights rather than taking the server down
+        match Weights::load(path) {
+            Ok(weights) => {
+                info!(?path, ?weights, "loaded weights");
+                self.weights = weights;
+                self.modified = modified;
+            }
+            Err(e) => error!(?path, "failed to load weights: {}", e),
+        }
+    }
+}
+
+/// Score of the leaf position, excluding the eating events already accumulated along the line.
+pub fn evaluate(state: &State, ate: bool) -> isize {
+    let weights = &state.weights;
+    let head = state.snake.get_head();
+    let mut score = weights.health * state.health as isize;
+
+    if !ate {
+        score += weights.food_distance * state.distance_to_food();
+    }
+
+    if weights.hazard != 0 && state.hazards.contains(&head) {
+        score += weights.hazard;
+    }
+
+    if weights.space != 0 || weights.territory != 0 {
+        let ours = distances(state, head);
+        score += weights.space * count_reachable(&ours) as isize;
+
+        if weights.territory != 0 {
+            let theirs: Vec<_> = state
+                .other_snakes
+                .iter()
+                .map(|s| distances(state, s.get_head()))
+                .collect();
+
+            let territory = (0..ours.len())
+                .filter(|&i| ours[i] != UNREACHABLE && theirs.iter().all(|t| ours[i] < t[i]))
+                .count();
+            score += weights.territory * territory as isize;
+        }
+    }
+
+    if weights.aggression != 0 {
+        let length = state.snake.len();
+        let closest = state
+            .other_snakes
+            .iter()
+            .filter(|s| s.len() < length)
+            .map(|s| head.manhattan_distance(&s.get_head()) as isize)
+            .min();
+
+        if let Some(distance) = closest {
+            score -= weights.aggression * distance;
+        }
+    }
+
+    score
+}
+
+const UNREACHABLE: u16 = u16::MAX;
+
+/// Cells reachable from `from`, itself included.
+pub(super) fn reachable(state: &State, from: Point) -> usize {
+    count_reachable(&distances(state, from))
+}
+
+fn count_reachable(distances: &[u16]) -> usize {
+    distances.iter().filter(|&&d| d != UNREACHABLE).count()
+}
+
+fn distances(state: &State, from: Point) -> Vec<u16> {
+    let index = |p: Point| (p.y * state.width + p.x) as usize;
+    let mut distances = vec![UNREACHABLE; (state.width * state.height) as usize];
+    let mut queue = VecDeque::new();
+
+    if !state.in_bounds(from) {
+        return distances;
+    }
+
+    distances[index(from)] = 0;
+    queue.push_back(from);
+
+    while let Some(p) = queue.pop_front() {
+        let distance = distances[index(p)] + 1;
+        for next in [
+            Point { x: p.x + 1, ..p },
+            Point { x: p.x - 1, ..p },
+            Point { y: p.y + 1, ..p },
+            Point { y: p.y - 1, ..p },
+        ] {
+            if !state.in_bounds(next) {
+                continue;
+            }
+            if distances[index(next)] != UNREACHABLE || state.collides_with(next) {
+                continue;
+            }
+            distances[index(next)] = distance;
+            queue.push_back(next);
+        }
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::food::FoodSpawns;
+
+    use super::*;
+
+    fn state(weights: Weights) -> State {
+        /*
+        .....
+        .....
+        ..x..
+        ..x..
+        ..x.e
+        */
+        State {
+            snake: vec![
+                Point { x: 2, y: 2 },
+                Point { x: 2, y: 1 },
+                Point { x: 2, y: 0 },
+            ]
+            .into(),
+            food: vec![Point { x: 0, y: 4 }],
+            other_snakes: vec![vec![Point { x: 4, y: 0 }].into()],
+            food_spawns: FoodSpawns::none(),
+            weights,
+            health: 50,
+            hazards: vec![Point { x: 2, y: 2 }],
+            hazard_damage: 14,
+            width: 5,
+            height: 5,
+            stats: Default::default(),
+            cancelled: Default::default(),
+            table: Default::default(),
+            sample: 0,
+            order: Default::default(),
+            pv: Vec::new(),
+            tie_seed: 0,
+        }
+    }
+
+    #[test]
+    fn default_weights_score_food_distance() {
+        let state = state(Weights::default());
+
+        assert_eq!(evaluate(&state, false), 4);
+        assert_eq!(evaluate(&state, true), 0);
+    }
+
+    #[test]
+    fn weighted_terms() {
+        let only = |weights: Weights| {
+            evaluate(
+                &state(Weights {
+                    length: 0,
+                    enemy_length: 0,
+                    food_distance: 0,
+                    ..weights
+                }),
+                true,
+            )
+        };
+
+        assert_eq!(
+            only(Weights {
+                health: 2,
+                ..Default::default()
+            }),
+            100
+        );
+        assert_eq!(
+            only(Weights {
+                hazard: -7,
+                ..Default::default()
+            }),
+            -7
+        );
+        // Everything but our neck, the tail moves out of the way
+        assert_eq!(
+            only(Weights {
+                space: 1,
+                ..Default::default()
+            }),
+            24
+        );
+        assert_eq!(
+            only(Weights {
+                aggression: 1,
+                ..Default::default()
+            }),
+            -4
+        );
+        // The enemy in the corner claims the cells closer to it
+        assert_eq!(
+            only(Weights {
+                territory: 1,
+                ..Default::default()
+            }),
+            14
+        );
+    }
+
+    #[test]
+    fn partial_weights_file() {
+        let weights: Weights = serde_json::from_str(r#"{"space": 3}"#).unwrap();
+
+        assert_eq!(
+            weights,
+            Weights {
+                space: 3,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use rand::Rng;
+
+use crate::requests::{Point, RulesetSettings};
+
+/// One sampled determinization of where food appears during the search horizon.
+///
+/// The engine spawns food after every turn: up to `minimumFood` if the board is
+/// short, otherwise a single piece with `foodSpawnChance` percent probability.
+/// The chance roll and a handful of candidate cells are drawn up front per turn,
+/// so the search stays deterministic and can undo spawns on the way back up.
+#[derive(Clone)]
+pub(crate) struct FoodSpawns {
+    minimum_food: usize,
+    turns: Vec<Spawn>,
+}
+
+#[derive(Clone)]
+struct Spawn {
+    chance: bool,
+    candidates: Vec<Point>,
+}
+
+impl FoodSpawns {
+    /// No food ever appears, the search only sees the food on the board.
+    pub fn none() -> Self {
+        Self {
+            minimum_food: 0,
+            turns: Vec::new(),
+        }
+    }
+
+    pub fn sample(
+        rng: &mut impl Rng,
+        settings: &RulesetSettings,
+        turns: usize,
+        width: i32,
+        height: i32,
+    ) -> Self {
+        let minimum_food = settings.minimum_food as usize;
+        // A few spares, candidates landing on snakes or food are skipped.
+        let candidates = minimum_food.max(1) + 2;
+
+        let turns = (0..turns)
+            .map(|_| Spawn {
+                chance: rng.gen_range(0..100) < settings.food_spawn_chance,
+                candidates: (0..candidates)
+                    .map(|_| Point {
+                        x: rng.gen_range(0..width),
+                        y: rng.gen_range(0..height),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            minimum_food,
+            turns,
+        }
+    }
+
+    /// Whether the settings can ever put new food on the board.
+    pub fn spawns_food(settings: &RulesetSettings) -> bool {
+        settings.minimum_food > 0 || settings.food_spawn_chance > 0
+    }
+
+    /// Food to place after `turn` given `food_count` pieces currently on the board.
+    /// `is_free` filters out cells that are occupied.
+    pub fn spawns_for(
+        &self,
+        turn: usize,
+        food_count: usize,
+        is_free: impl Fn(Point) -> bool,
+    ) -> Vec<Point> {
+        let Some(spawn) = self.turns.get(turn) else {
+            return Vec::new();
+        };
+
+        let needed = if food_count < self.minimum_food {
+            self.minimum_food - food_count
+        } else if spawn.chance {
+            1
+        } else {
+            0
+        };
+
+        let mut spawned: Vec<Point> = Vec::with_capacity(needed);
+        for &p in &spawn.candidates {
+            if spawned.len() == needed {
+                break;
+            }
+            if is_free(p) && !spawned.contains(&p) {
+                spawned.push(p);
+            }
+        }
+
+        spawned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn settings(minimum_food: u32, food_spawn_chance: u32) -> RulesetSettings {
+        RulesetSettings {
+            minimum_food,
+            food_spawn_chance,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tops_up_to_minimum_food() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let spawns = FoodSpawns::sample(&mut rng, &settings(2, 0), 3, 11, 11);
+
+        assert_eq!(spawns.spawns_for(0, 0, |_| true).len(), 2);
+        assert_eq!(spawns.spawns_for(1, 1, |_| true).len(), 1);
+        assert_eq!(spawns.spawns_for(2, 2, |_| true).len(), 0);
+    }
+
+    #[test]
+    fn always_spawns_on_full_chance() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let spawns = FoodSpawns::sample(&mut rng, &settings(0, 100), 2, 11, 11);
+
+        assert_eq!(spawns.spawns_for(0, 5, |_| true).len(), 1);
+        assert_eq!(spawns.spawns_for(1, 5, |_| true).len(), 1);
+        // Beyond the sampled horizon nothing spawns
+        assert_eq!(spawns.spawns_for(2, 5, |_| true).len(), 0);
+    }
+
+    #[test]
+    fn skips_occupied_cells() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let spawns = FoodSpawns::sample(&mut rng, &settings(1, 0), 1, 11, 11);
+
+        assert!(spawns.spawns_for(0, 0, |_| false).is_empty());
+        assert!(FoodSpawns::none().spawns_for(0, 0, |_| true).is_empty());
+    }
+}
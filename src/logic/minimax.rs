@@ -1,14 +1,36 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::atomic::Ordering,
+};
+
 use tracing::debug;
 
+use crate::logic::eval::evaluate;
 use crate::logic::moves::{get_moves, movement_to_move};
-
-use super::State;
-
-pub fn minimax<const DEPTH: u8, const WIDTH: i32, const HEIGHT: i32>(state: &mut State) -> isize {
-    minimax_impl2::<DEPTH, WIDTH, HEIGHT>(state, 0, 0, Minimax::Maximize)
+use crate::logic::order::pack;
+use crate::logic::table::{Bound, Entry};
+use crate::requests::Point;
+
+use super::{Ply, State, MAX_HEALTH};
+
+/// Scores `state` right after we made our move, the other snakes answer it first.
+pub fn minimax<const DEPTH: u8>(state: &mut State) -> isize {
+    minimax_impl2::<DEPTH>(
+        state,
+        0,
+        0,
+        Minimax::Minimize,
+        Window {
+            alpha: isize::MIN,
+            beta: isize::MAX,
+        },
+    )
 }
 
-fn minimax_impl<const DEPTH: u8, const WIDTH: i32, const HEIGHT: i32>(
+// Searches only our own moves, from before the other snakes were searched. Unused.
+#[allow(dead_code, clippy::needless_borrow, clippy::redundant_pattern_matching)]
+fn minimax_impl<const DEPTH: u8>(
     mut state: &mut State,
     depth: u8,
     mut current_score: isize,
@@ -38,7 +60,7 @@ fn minimax_impl<const DEPTH: u8, const WIDTH: i32, const HEIGHT: i32>(
         };
     }
 
-    let possibilities = get_moves::<WIDTH, HEIGHT>(&state.snake, &state);
+    let possibilities = get_moves(&state.snake, &state);
     possibilities
         .iter()
         .enumerate()
@@ -48,7 +70,7 @@ fn minimax_impl<const DEPTH: u8, const WIDTH: i32, const HEIGHT: i32>(
             let new_head = movement_to_move(head, movement);
             let old_head = state.snake.push_head(new_head);
 
-            let score = minimax_impl::<DEPTH, WIDTH, HEIGHT>(&mut state, depth + 1, current_score);
+            let score = minimax_impl::<DEPTH>(&mut state, depth + 1, current_score);
 
             state.uneat(ate);
             state.snake.pop_head(old_head);
@@ -59,21 +81,55 @@ fn minimax_impl<const DEPTH: u8, const WIDTH: i32, const HEIGHT: i32>(
         .unwrap_or(isize::MIN)
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum Minimax {
     Minimize,
     Maximize,
 }
 
-fn minimax_impl2<const DEPTH: u8, const WIDTH: i32, const HEIGHT: i32>(
-    mut state: &mut State,
+/// Scores outside `alpha..beta` can't change the result, the search stops once it knows
+/// it is out.
+#[derive(Clone, Copy)]
+struct Window {
+    alpha: isize,
+    beta: isize,
+}
+
+fn minimax_impl2<const DEPTH: u8>(
+    state: &mut State,
     depth: u8,
     mut current_score: isize,
     minimax: Minimax,
+    window: Window,
 ) -> isize {
-    let head = state.snake.get_head();
-
     debug!(?state.snake);
+    clear_line(state, depth);
+    // Unwinds without touching the state, the caller discards the result
+    if state.cancelled.load(Ordering::Relaxed) {
+        return 0;
+    }
+    if depth > 0 {
+        state.stats.nodes += 1;
+    }
+
+    // The root moves are all different positions, nothing to look up there
+    let key = (depth > 0).then(|| key(state, depth, minimax));
+    let stored = key.and_then(|key| state.table.get(key));
+    if let Some(entry) = stored.filter(|e| e.remaining == DEPTH - depth) {
+        let value = absolute(entry.value, current_score);
+        // A value inside the window goes on the line, which the table doesn't keep
+        let usable = match entry.bound {
+            Bound::Exact => value <= window.alpha || value >= window.beta,
+            Bound::Lower => value >= window.beta,
+            Bound::Upper => value <= window.alpha,
+        };
+        if usable {
+            state.stats.tt_hits += 1;
+            return value;
+        }
+    }
 
+    let score = current_score;
     let ate = state.try_eat(state.snake.get_head());
     let other_ate: Vec<_> = state.other_snakes.iter().map(|s| s.get_head()).collect();
 
@@ -82,103 +138,247 @@ fn minimax_impl2<const DEPTH: u8, const WIDTH: i32, const HEIGHT: i32>(
         .filter_map(|p| state.try_eat(p))
         .collect();
 
+    let health = state.health;
+
     // Score for you eating
-    if let Some(_) = ate {
-        current_score += 100;
+    if ate.is_some() {
+        current_score += state.weights.length;
+        state.health = MAX_HEALTH;
     }
 
-    current_score -= (other_ate.len() * 10) as isize;
+    current_score += other_ate.len() as isize * state.weights.enemy_length;
+
+    let (value, best) = if state.health <= 0 {
+        // Starved
+        (isize::MIN, None)
+    } else if depth == DEPTH {
+        // Exit condition
+        (current_score + evaluate(state, ate.is_some()), None)
+    } else {
+        let best = stored.and_then(|e| e.best);
+        search::<DEPTH>(state, depth, current_score, minimax, window, best)
+    };
+
+    state.health = health;
+    state.uneat(ate);
+    other_ate.iter().for_each(|&f| state.uneat(Some(f)));
+
+    // A cancelled subtree scored 0 everywhere, keep it out of the table
+    if let Some(key) = key {
+        if !state.cancelled.load(Ordering::Relaxed) {
+            let bound = if best.is_none() {
+                // Not searched, or nothing to search
+                Bound::Exact
+            } else if value <= window.alpha {
+                Bound::Upper
+            } else if value >= window.beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            state.table.insert(
+                key,
+                Entry {
+                    remaining: DEPTH - depth,
+                    value: relative(value, score),
+                    bound,
+                    best,
+                },
+            );
+        }
+    }
 
-    // Exit condition
-    if depth == DEPTH {
-        other_ate.iter().for_each(|&f| state.uneat(Some(f)));
+    value
+}
 
-        return match ate {
-            Some(p) => {
-                state.uneat(Some(p));
-                current_score
-            }
-            None => {
-                let distance_to_food = state.distance_to_food();
-                current_score + distance_to_food
-            }
-        };
+// The line below `depth` is built on the way back up, empty until then
+fn clear_line(state: &mut State, depth: u8) {
+    let depth = depth as usize;
+    if state.pv.len() <= depth {
+        state.pv.resize_with(depth + 1, Vec::new);
+    }
+    state.pv[depth].clear();
+}
+
+// Makes `ply` followed by the line found below it the line at `depth`
+fn extend_line(state: &mut State, depth: u8, ply: Ply) {
+    let depth = depth as usize;
+    let mut line = Vec::with_capacity(state.pv[depth + 1].len() + 1);
+    // Alone on the board, there is nobody else to move
+    if ply != Ply::Others(Vec::new()) {
+        line.push(ply);
     }
+    line.extend_from_slice(&state.pv[depth + 1]);
+    state.pv[depth] = line;
+}
+
+/// Identifies a position, `depth` picks the food spawns still to come.
+fn key(state: &State, depth: u8, minimax: Minimax) -> u64 {
+    let mut food = state.food.clone();
+    food.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    (depth, minimax == Minimax::Maximize).hash(&mut hasher);
+    (state.sample, state.health, food).hash(&mut hasher);
+    state.snake.hash(&mut hasher);
+    state.other_snakes.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Scores carry what was gained on the way down, the table stores only what the position
+// adds to it. Wins and losses are stored as they are.
+fn relative(value: isize, score: isize) -> isize {
+    match value {
+        isize::MIN | isize::MAX => value,
+        _ => value - score,
+    }
+}
+
+fn absolute(stored: isize, score: isize) -> isize {
+    match stored {
+        isize::MIN | isize::MAX => stored,
+        _ => stored + score,
+    }
+}
+
+/// Searches every move at a node, best first, returning its score and the packed best move.
+fn search<const DEPTH: u8>(
+    state: &mut State,
+    depth: u8,
+    current_score: isize,
+    minimax: Minimax,
+    mut window: Window,
+    best: Option<u32>,
+) -> (isize, Option<u32>) {
+    let head = state.snake.get_head();
+    state.stats.expanded += 1;
+    let remaining = DEPTH - depth;
 
     match minimax {
         Minimax::Minimize => {
             let mut value = isize::MAX;
+            let mut best_moves = None;
             let mut old_snakes = Vec::with_capacity(state.other_snakes.len());
 
+            let heads: Vec<Point> = state.other_snakes.iter().map(|s| s.get_head()).collect();
             let move_sets: Vec<Vec<_>> = state
                 .other_snakes
                 .iter()
                 .map(|s| {
-                    get_moves::<WIDTH, HEIGHT>(s, &state)
+                    get_moves(s, state)
                         .iter()
                         .enumerate()
-                        .filter(|(_, p)| **p)
+                        // Our head isn't in their way, meeting it is a head-to-head
+                        .filter(|&(i, &p)| p || movement_to_move(s.get_head(), i.into()) == head)
                         .map(|(i, _)| i.into())
                         .collect()
                 })
                 .collect();
 
-            let moves = permutations(&move_sets);
+            let mut moves = permutations(&move_sets);
+            state.order.sort(&mut moves, &heads, false, depth, best);
 
-            for move_set in moves {
-                for s in 0..state.other_snakes.len() {
-                    let movement = move_set[s];
-                    let new_head = movement_to_move(state.other_snakes[s].get_head(), movement);
-                    let old_head = state.other_snakes[s].push_head(new_head);
+            for (tried, move_set) in moves.iter().enumerate() {
+                for (snake, &movement) in state.other_snakes.iter_mut().zip(move_set) {
+                    let new_head = movement_to_move(snake.get_head(), movement);
+                    let old_head = snake.push_head(new_head);
                     old_snakes.push(old_head);
                 }
 
-                let score = minimax_impl2::<DEPTH, WIDTH, HEIGHT>(
-                    &mut state,
-                    depth + 1,
-                    current_score,
-                    Minimax::Maximize,
-                );
-
-                value = value.min(score);
+                // Running into a head at least as long as ours kills us
+                let lost_head_to_head = state
+                    .other_snakes
+                    .iter()
+                    .any(|s| s.get_head() == head && s.len() >= state.snake.len());
+
+                let score = if lost_head_to_head {
+                    clear_line(state, depth + 1);
+                    isize::MIN
+                } else {
+                    // Everyone has moved, the engine spawns food before the next turn
+                    let spawned = state.spawn_food(depth as usize / 2);
+                    let score = minimax_impl2::<DEPTH>(
+                        state,
+                        depth + 1,
+                        current_score,
+                        Minimax::Maximize,
+                        window,
+                    );
+                    state.unspawn_food(&spawned);
+                    score
+                };
 
                 for (i, &h) in old_snakes.iter().enumerate() {
                     state.other_snakes[i].pop_head(h);
                 }
                 old_snakes.clear();
-            }
 
-            state.uneat(ate);
-            other_ate.iter().for_each(|&f| state.uneat(Some(f)));
+                if best_moves.is_none() || score < value {
+                    value = score;
+                    best_moves = Some(pack(move_set));
+                    // Lines outside the window never make it to the root
+                    if score < window.beta {
+                        extend_line(state, depth, Ply::Others(move_set.clone()));
+                    }
+                }
+                window.beta = window.beta.min(value);
+                if window.beta <= window.alpha {
+                    state.stats.cutoffs += (moves.len() - tried - 1) as u64;
+                    state
+                        .order
+                        .cutoff(move_set, &heads, false, depth, remaining);
+                    break;
+                }
+            }
 
-            return value;
+            (value, best_moves)
         }
         Minimax::Maximize => {
-            let possibilities = get_moves::<WIDTH, HEIGHT>(&state.snake, &state);
-            possibilities
+            let mut value = isize::MIN;
+            let mut best_move = None;
+
+            let mut moves: Vec<Vec<_>> = get_moves(&state.snake, state)
                 .iter()
                 .enumerate()
                 .filter(|(_, p)| **p)
-                .map(|(i, _)| i.into())
-                .map(|movement| {
-                    let new_head = movement_to_move(head, movement);
-                    let old_head = state.snake.push_head(new_head);
+                .map(|(i, _)| vec![i.into()])
+                .collect();
+            state.order.sort(&mut moves, &[head], true, depth, best);
 
-                    let score = minimax_impl2::<DEPTH, WIDTH, HEIGHT>(
-                        &mut state,
-                        depth + 1,
-                        current_score,
-                        Minimax::Minimize,
-                    );
+            for (tried, movement) in moves.iter().enumerate() {
+                let new_head = movement_to_move(head, movement[0]);
+                let old_head = state.snake.push_head(new_head);
+                let health = state.step_health(new_head);
 
-                    state.uneat(ate);
-                    other_ate.iter().for_each(|&f| state.uneat(Some(f)));
-                    state.snake.pop_head(old_head);
+                let score = minimax_impl2::<DEPTH>(
+                    state,
+                    depth + 1,
+                    current_score,
+                    Minimax::Minimize,
+                    window,
+                );
 
-                    score
-                })
-                .max()
-                .unwrap_or(isize::MIN)
+                state.health = health;
+                state.snake.pop_head(old_head);
+
+                if best_move.is_none() || score > value {
+                    value = score;
+                    best_move = Some(pack(movement));
+                    if score > window.alpha {
+                        extend_line(state, depth, Ply::You(movement[0]));
+                    }
+                }
+                window.alpha = window.alpha.max(value);
+                if window.alpha >= window.beta {
+                    state.stats.cutoffs += (moves.len() - tried - 1) as u64;
+                    state
+                        .order
+                        .cutoff(movement, &[head], true, depth, remaining);
+                    break;
+                }
+            }
+
+            (value, best_move)
         }
     }
 }
@@ -210,8 +410,21 @@ fn permutations<T: Clone>(input: &[Vec<T>]) -> Vec<Vec<T>> {
 mod tests {
     use tracing::info_span;
 
+    use crate::{
+        logic::{
+            fallback, food::FoodSpawns, search_until, tie::TieBreak, Progress, Stats, Weights,
+        },
+        parse::parse,
+        render::Grid,
+        requests::Point,
+    };
+
     use super::*;
 
+    fn state(board: &str) -> State {
+        State::from_turn(parse(board).unwrap(), Weights::default())
+    }
+
     #[test]
     fn minimax_move_left() {
         /*
@@ -221,31 +434,26 @@ mod tests {
         f..x.
         .....
 
-        move left should be best
+        move left should be closest to food
         */
 
-        let mut snake: Snake = vec![
-            Point { x: 3, y: 1 },
-            Point { x: 3, y: 2 },
-            Point { x: 3, y: 3 },
-        ]
-        .into();
-        let food = vec![Point { x: 0, y: 1 }];
+        let mut state = state(".....\n...a.\n...a.\n*..A.\n.....");
+        let board = Grid::from_state(&state);
 
-        let h = snake.push_head(Point { x: 2, y: 1 });
-        let min_left = minimax::<0, 5, 5>(&mut snake, &food, 0);
+        let h = state.snake.push_head(Point { x: 2, y: 1 });
+        let min_left = minimax::<0>(&mut state);
 
-        snake.pop_head(h);
-        snake.push_head(Point { x: 3, y: 0 });
-        let min_down = minimax::<0, 5, 5>(&mut snake, &food, 0);
+        state.snake.pop_head(h);
+        state.snake.push_head(Point { x: 3, y: 0 });
+        let min_down = minimax::<0>(&mut state);
 
-        snake.pop_head(h);
-        snake.push_head(Point { x: 4, y: 1 });
-        let min_right = minimax::<0, 5, 5>(&mut snake, &food, 0);
+        state.snake.pop_head(h);
+        state.snake.push_head(Point { x: 4, y: 1 });
+        let min_right = minimax::<0>(&mut state);
 
-        assert_eq!(min_left, 2);
-        assert_eq!(min_down, 4);
-        assert_eq!(min_right, 4);
+        assert_eq!(min_left, 2, "\n{}", board);
+        assert_eq!(min_down, 4, "\n{}", board);
+        assert_eq!(min_right, 4, "\n{}", board);
     }
 
     #[test]
@@ -257,25 +465,20 @@ mod tests {
         ...21
         ..f3.
 
-        move left should be best
+        down should be closest to food
         */
 
-        let mut snake: Snake = vec![
-            Point { x: 4, y: 1 },
-            Point { x: 3, y: 1 },
-            Point { x: 3, y: 0 },
-        ]
-        .into();
-        let food = vec![Point { x: 2, y: 0 }];
+        let mut state = state(".....\n.....\n.....\n...aA\n..*a.");
+        let board = Grid::from_state(&state);
 
-        let h = snake.push_head(Point { x: 4, y: 0 });
-        let min_down = minimax::<0, 5, 5>(&mut snake, &food, 0);
+        let h = state.snake.push_head(Point { x: 4, y: 0 });
+        let min_down = minimax::<0>(&mut state);
 
-        snake.pop_head(h);
-        snake.push_head(Point { x: 4, y: 2 });
-        let min_up = minimax::<0, 5, 5>(&mut snake, &food, 0);
+        state.snake.pop_head(h);
+        state.snake.push_head(Point { x: 4, y: 2 });
+        let min_up = minimax::<0>(&mut state);
 
-        assert!(min_down < min_up);
+        assert!(min_down < min_up, "\n{}", board);
     }
 
     #[test]
@@ -299,26 +502,20 @@ mod tests {
         ....3
         ..f12
 
-        move left should be best
+        down should still be closest to food after another move
         */
-        let mut snake: Snake = vec![
-            Point { x: 4, y: 1 },
-            Point { x: 3, y: 1 },
-            Point { x: 3, y: 0 },
-        ]
-        .into();
-        let food = vec![Point { x: 2, y: 0 }];
-
-        let h = snake.push_head(Point { x: 4, y: 0 });
-        let min_down = info_span!("down").in_scope(|| minimax::<1, 5, 5>(&mut snake, &food, 1));
-
-        snake.pop_head(h);
-        snake.push_head(Point { x: 4, y: 2 });
-        let min_up = info_span!("up").in_scope(|| minimax::<1, 5, 5>(&mut snake, &food, 1));
-
-        assert!(min_down < min_up);
-        assert_eq!(min_down, 1);
-        assert_eq!(min_up, 3);
+        let mut state = state(".....\n.....\n.....\n...aA\n..*a.");
+        let board = Grid::from_state(&state);
+
+        // The others answer first, so one more move of ours is two plies
+        let h = state.snake.push_head(Point { x: 4, y: 0 });
+        let min_down = info_span!("down").in_scope(|| minimax::<2>(&mut state));
+
+        state.snake.pop_head(h);
+        state.snake.push_head(Point { x: 4, y: 2 });
+        let min_up = info_span!("up").in_scope(|| minimax::<2>(&mut state));
+
+        assert!(min_down < min_up, "\n{}", board);
     }
 
     #[test]
@@ -343,24 +540,229 @@ mod tests {
 
         let _ = tracing_subscriber::fmt::try_init();
 
-        let mut snake: Snake = vec![
-            Point { x: 4, y: 2 },
-            Point { x: 3, y: 2 },
-            Point { x: 3, y: 1 },
-            Point { x: 3, y: 0 },
-        ]
-        .into();
-        let food = vec![Point { x: 2, y: 1 }];
-
-        let h = snake.push_head(Point { x: 4, y: 1 });
-        let min_down = info_span!("down").in_scope(|| minimax::<2, 5, 5>(&mut snake, &food, 2));
-
-        snake.pop_head(h);
-        snake.push_head(Point { x: 4, y: 3 });
-        let min_up = info_span!("up").in_scope(|| minimax::<2, 5, 5>(&mut snake, &food, 2));
-
-        assert!(min_down < min_up);
-        assert_eq!(min_down, 0);
-        assert_eq!(min_up, 2);
+        let mut state = state(".....\n.....\n...aA\n..*a.\n...a.");
+        let board = Grid::from_state(&state);
+
+        let h = state.snake.push_head(Point { x: 4, y: 1 });
+        let min_down = info_span!("down").in_scope(|| minimax::<3>(&mut state));
+
+        state.snake.pop_head(h);
+        state.snake.push_head(Point { x: 4, y: 3 });
+        let min_up = info_span!("up").in_scope(|| minimax::<3>(&mut state));
+
+        assert!(min_down < min_up, "\n{}", board);
+    }
+
+    #[test]
+    fn food_spawns_are_undone() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut state = state(".....\n.....\n..A..\n..a..\n*.a..");
+        let food = state.food.clone();
+        let settings = crate::requests::RulesetSettings {
+            minimum_food: 3,
+            food_spawn_chance: 100,
+            ..Default::default()
+        };
+        state.food_spawns = FoodSpawns::sample(&mut StdRng::seed_from_u64(7), &settings, 3, 5, 5);
+
+        minimax::<4>(&mut state);
+
+        assert_eq!(state.food, food);
+    }
+
+    #[test]
+    fn sampled_food_is_eaten() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        // Nothing to eat on the board until the engine tops it up to one piece
+        let mut state = state("...\n.A.\n.aa");
+        let without = minimax::<4>(&mut state);
+
+        let settings = crate::requests::RulesetSettings {
+            minimum_food: 1,
+            food_spawn_chance: 0,
+            ..Default::default()
+        };
+        state.food_spawns = FoodSpawns::sample(&mut StdRng::seed_from_u64(7), &settings, 2, 3, 3);
+        let with = minimax::<4>(&mut state);
+
+        assert_eq!(without, 0);
+        assert_eq!(with, state.weights.length);
+        assert!(state.food.is_empty());
+    }
+
+    #[test]
+    fn samples_combine_in_any_order() {
+        use crate::logic::combine;
+
+        let (win, loss) = (isize::MAX, isize::MIN);
+        assert_eq!(combine(&[win, win, win]), win);
+        assert_eq!(combine(&[loss, loss, loss]), loss);
+        assert_eq!(combine(&[3, 5, 7]), 5);
+
+        // A coin flip is neither a win, a loss nor an even position
+        let flip = combine(&[win, loss, 10]);
+        assert_eq!(flip, combine(&[10, loss, win]));
+        assert_eq!(flip, combine(&[loss, 10, win]));
+        assert!(loss < flip && flip < combine(&[-1000, -1000, -1000]));
+
+        // Surviving every sample comes first, then winning the most of them
+        assert!(combine(&[win, 10, 10]) > combine(&[10, 10, 10]));
+        assert!(combine(&[win, win, loss]) < combine(&[-1000, -1000, -1000]));
+        assert!(combine(&[win, win, loss]) > combine(&[win, 10, loss]));
+        assert!(combine(&[win, win, 10]) < win);
+    }
+
+    #[test]
+    fn search_until_deadline() {
+        use std::time::{Duration, Instant};
+
+        let board = ".....\n.....\n..A..\n..a..\n*.a..";
+
+        let search = |deadline, progress: &Progress| {
+            search_until(
+                parse(board).unwrap(),
+                Weights::default(),
+                TieBreak::default(),
+                deadline,
+                2,
+                progress,
+            )
+        };
+
+        let progress = Progress::default();
+        let past = search(Instant::now(), &progress).unwrap();
+        assert_eq!(past.depth, 2);
+        assert_eq!(progress.best().unwrap().depth, 2);
+
+        let later = search(Instant::now() + Duration::from_secs(60), &progress).unwrap();
+        assert!(later.depth > 2, "only reached depth {}", later.depth);
+
+        let cancelled = Progress::default();
+        cancelled.cancel();
+        assert!(search(Instant::now() + Duration::from_secs(60), &cancelled).is_none());
+        assert!(cancelled.best().is_none());
+    }
+
+    #[test]
+    fn principal_variation() {
+        let (mut state, determinizations) =
+            crate::logic::prepare(parse("....\n.A..\n.a..\n...B").unwrap(), Weights::default());
+        let search =
+            crate::logic::search_depth(&mut state, &determinizations, 4, 1, TieBreak::default());
+
+        // Our move, theirs, ours and theirs again, down to the horizon
+        let pv = search.pv();
+        assert_eq!(pv.len(), 5, "{:?}", pv);
+        assert_eq!(pv[0], Ply::You(search.movement));
+        for (i, ply) in pv.iter().enumerate() {
+            assert_eq!(matches!(ply, Ply::You(_)), i % 2 == 0, "{:?}", pv);
+        }
+    }
+
+    #[test]
+    fn fallback_avoids_walls() {
+        let search = fallback(parse("A..\na..\n...").unwrap());
+        assert_eq!(search.movement, crate::responses::Movement::Right);
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        // Threads take the moves in any order, on an open board that can pick other lines
+        // of the same score
+        let turn = parse("..*\n.A.\n.a.\n.a.\nB..").unwrap();
+
+        let search = |threads| {
+            let (mut state, determinizations) =
+                crate::logic::prepare(turn.clone(), Weights::default());
+            crate::logic::search_depth(
+                &mut state,
+                &determinizations,
+                4,
+                threads,
+                TieBreak::default(),
+            )
+        };
+        let sequential = search(1);
+        let parallel = search(4);
+
+        assert_eq!(parallel.movement, sequential.movement);
+        assert_eq!(parallel.candidates, sequential.candidates);
+    }
+
+    #[test]
+    fn ordering_keeps_scores() {
+        // Narrow enough that no two lines tie, so the lines have to match as well. Without
+        // spawns there's no food sample to make them tie either
+        let mut turn = parse("..*\n.A.\n.a.\n.a.\nB..").unwrap();
+        turn.game.ruleset.settings.minimum_food = 0;
+        turn.game.ruleset.settings.food_spawn_chance = 0;
+        let (mut state, determinizations) = crate::logic::prepare(turn, Weights::default());
+
+        // The second pass starts from the best moves and refutations the first one found
+        let first =
+            crate::logic::search_depth(&mut state, &determinizations, 6, 1, TieBreak::default());
+        state.table = Default::default();
+        let second =
+            crate::logic::search_depth(&mut state, &determinizations, 6, 1, TieBreak::default());
+
+        assert!(first.stats.cutoffs > 0);
+        assert_eq!(first.candidates, second.candidates);
+
+        // Answered from the table this time, the lines still have to come out whole
+        let warm =
+            crate::logic::search_depth(&mut state, &determinizations, 6, 1, TieBreak::default());
+        assert!(warm.stats.tt_hits > 0);
+        assert_eq!(warm.candidates, second.candidates);
+    }
+
+    #[test]
+    fn ties_follow_the_policy() {
+        use crate::responses::Movement;
+
+        // Alone with nothing weighted, every move scores the same
+        let turn = parse(".....\n.....\n.....\n.Aaa.\n.....").unwrap();
+        let search = |tie_break| {
+            let weights = Weights {
+                length: 0,
+                food_distance: 0,
+                ..Default::default()
+            };
+            let (mut state, determinizations) = crate::logic::prepare(turn.clone(), weights);
+            crate::logic::search_depth(&mut state, &determinizations, 6, 1, tie_break)
+        };
+
+        let first = search(TieBreak::First);
+        assert_eq!(first.candidates.len(), 3);
+        assert!(first.candidates.iter().all(|c| c.score == 0));
+        assert_eq!(first.movement, Movement::Left);
+
+        assert_eq!(search(TieBreak::Centre).movement, Movement::Up);
+        assert_eq!(search(TieBreak::Space).movement, Movement::Up);
+
+        let random = search(TieBreak::Random).movement;
+        assert_eq!(search(TieBreak::Random).movement, random);
+        assert!(first.candidates.iter().any(|c| c.movement == random));
+    }
+
+    #[test]
+    fn counts_nodes_and_expansions() {
+        // Alone with three ways to go, the others' turn expands into ours and its leaves
+        let mut state = state("...\n.A.\n.a.\n.a.");
+        minimax::<2>(&mut state);
+
+        let stats = state.stats;
+        assert_eq!(stats.nodes, 4);
+        assert_eq!(stats.expanded, 2);
+        assert_eq!(stats.branching(), 2.0);
+
+        let mut total = Stats::default();
+        assert_eq!(total.branching(), 0.0);
+        total.add(&stats);
+        total.add(&stats);
+        assert_eq!(total.nodes, 8);
+        assert_eq!(total.expanded, 4);
+        assert_eq!(total.branching(), stats.branching());
     }
 }
@@ -1,30 +1,105 @@
+mod eval;
+mod food;
 mod minimax;
 mod moves;
+mod order;
+mod seed;
+mod table;
+mod tie;
 
+pub use crate::logic::eval::{Weights, WeightsFile};
+use crate::logic::food::FoodSpawns;
 use crate::logic::minimax::minimax;
 use crate::logic::moves::{get_moves, movement_to_move};
+use crate::logic::order::MoveOrder;
+pub use crate::logic::seed::{force_seed, rng};
+use crate::logic::table::Table;
+pub use crate::logic::tie::TieBreak;
 
-use std::time::Instant;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info};
 
 use crate::{
+    render::Grid,
     requests::{Point, Turn},
     responses::{Move, Movement},
     snake::Snake,
 };
 
-const WIDTH: i32 = 11;
-const HEIGHT: i32 = 11;
 const DEPTH: u8 = 7;
+// Deepest iteration `search_until` will try
+const MAX_DEPTH: u8 = 12;
+// Food spawn determinizations averaged per root move
+const FOOD_SAMPLES: usize = 3;
+const MAX_HEALTH: i32 = 100;
 
+#[derive(Clone)]
 pub(crate) struct State {
     pub(crate) snake: Snake,
     pub(crate) food: Vec<Point>,
     pub(crate) other_snakes: Vec<Snake>,
+    pub(crate) food_spawns: FoodSpawns,
+    pub(crate) weights: Weights,
+    pub(crate) health: i32,
+    pub(crate) hazards: Vec<Point>,
+    pub(crate) hazard_damage: i32,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    pub(crate) stats: Stats,
+    /// Set from outside to stop the search, every node then scores 0
+    pub(crate) cancelled: Arc<AtomicBool>,
+    /// Positions already scored, shared with the other root threads
+    pub(crate) table: Arc<Table>,
+    /// Which food determinization is being searched, it tells positions apart in `table`
+    pub(crate) sample: u8,
+    /// Refutations found so far, each root thread keeps its own
+    pub(crate) order: MoveOrder,
+    /// Best line found below each ply of the current path
+    pub(crate) pv: Vec<Vec<Ply>>,
+    /// Draws random tie breaks, the same at every depth of a turn
+    pub(crate) tie_seed: u64,
 }
 
 impl State {
+    pub fn from_turn(turn: Turn, weights: Weights) -> Self {
+        State {
+            snake: turn.you.body.into(),
+            food: turn.board.food,
+            other_snakes: turn
+                .board
+                .snakes
+                .into_iter()
+                .filter(|s| s.id != turn.you.id)
+                .map(|s| s.body.into())
+                .collect(),
+            food_spawns: FoodSpawns::none(),
+            weights,
+            health: turn.you.health,
+            hazards: turn.board.hazards,
+            hazard_damage: turn.game.ruleset.settings.hazard_damage_per_turn as i32,
+            width: turn.board.width,
+            height: turn.board.height,
+            stats: Stats::default(),
+            cancelled: Arc::default(),
+            table: Arc::default(),
+            sample: 0,
+            order: MoveOrder::default(),
+            pv: Vec::new(),
+            tie_seed: 0,
+        }
+    }
+
     pub fn distance_to_food(&self) -> isize {
         let head = self.snake.get_head();
         self.food
@@ -50,6 +125,32 @@ impl State {
         None
     }
 
+    /// Spend health for moving our head onto `head`, returning the health before the move.
+    pub fn step_health(&mut self, head: Point) -> i32 {
+        let health = self.health;
+        self.health -= 1;
+        if self.hazards.contains(&head) {
+            self.health -= self.hazard_damage;
+        }
+        health
+    }
+
+    pub fn spawn_food(&mut self, turn: usize) -> Vec<Point> {
+        let spawned = self.food_spawns.spawns_for(turn, self.food.len(), |p| {
+            !self.food.contains(&p) && !self.collides_with(p)
+        });
+        self.food.extend_from_slice(&spawned);
+        spawned
+    }
+
+    pub fn unspawn_food(&mut self, spawned: &[Point]) {
+        self.food.retain(|p| !spawned.contains(p));
+    }
+
+    pub fn in_bounds(&self, p: Point) -> bool {
+        p.x >= 0 && p.x < self.width && p.y >= 0 && p.y < self.height
+    }
+
     pub fn collides_with(&self, p: Point) -> bool {
         if self.snake.collides_with(p) {
             return true;
@@ -59,57 +160,478 @@ impl State {
     }
 }
 
-pub fn get_move(turn: Turn) -> Move {
-    let budget = Instant::now();
+/// Shared between a running `search_until` and whoever waits on it.
+#[derive(Default)]
+pub struct Progress {
+    cancelled: Arc<AtomicBool>,
+    best: Mutex<Option<Search>>,
+}
+
+impl Progress {
+    /// Stops the search, the iteration in flight is thrown away.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// The deepest iteration finished so far.
+    pub fn best(&self) -> Option<Search> {
+        self.best.lock().unwrap().clone()
+    }
+}
+
+/// Moves that don't immediately run into a wall or a body.
+pub fn safe_moves(turn: Turn) -> Vec<Movement> {
+    let state = State::from_turn(turn, Weights::default());
+
+    get_moves(&state.snake, &state)
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| **p)
+        .map(|(i, _)| i.into())
+        .collect()
+}
+
+/// Names of the other snakes left with at most one safe move.
+pub fn trapped(turn: Turn) -> Vec<String> {
+    let names: Vec<_> = turn
+        .board
+        .snakes
+        .iter()
+        .filter(|s| s.id != turn.you.id)
+        .map(|s| s.name.clone())
+        .collect();
+    let state = State::from_turn(turn, Weights::default());
+
+    state
+        .other_snakes
+        .iter()
+        .zip(names)
+        .filter(|(snake, _)| get_moves(snake, &state).iter().filter(|&&p| p).count() <= 1)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// A root move and the score the search gave it.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Candidate {
+    pub movement: Movement,
+    pub score: isize,
+    /// The line the search expects after the move, starting with it
+    #[serde(default)]
+    pub pv: Vec<Ply>,
+}
+
+impl Candidate {
+    /// Whatever food spawns, the search corners an opponent after this move. Only a win
+    /// against the last one, the search stops at the first snake out of moves.
+    pub fn wins(&self) -> bool {
+        self.score == isize::MAX
+    }
+
+    /// Whatever food spawns, the search found no way to survive after this move.
+    pub fn loses(&self) -> bool {
+        self.score == isize::MIN
+    }
+}
+
+/// One step of a principal variation.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum Ply {
+    You(Movement),
+    /// Every other snake's move, in board order
+    Others(Vec<Movement>),
+}
+
+impl fmt::Display for Ply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ply::You(movement) => write!(f, "{}", name(*movement)),
+            Ply::Others(movements) => {
+                let names: Vec<_> = movements.iter().map(|&m| name(m)).collect();
+                write!(f, "[{}]", names.join(" "))
+            }
+        }
+    }
+}
+
+fn name(movement: Movement) -> &'static str {
+    match movement {
+        Movement::Right => "right",
+        Movement::Left => "left",
+        Movement::Up => "up",
+        Movement::Down => "down",
+    }
+}
+
+/// A principal variation on one line, our moves bare and the others' in brackets.
+pub fn line(pv: &[Ply]) -> String {
+    pv.iter().map(Ply::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// Counters for how much work a search did.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone, Default)]
+#[serde(default)]
+pub struct Stats {
+    /// Positions searched below the root moves
+    pub nodes: u64,
+    /// Nodes whose children were searched
+    pub expanded: u64,
+    /// Nodes answered by the transposition table
+    pub tt_hits: u64,
+    /// Moves skipped by alpha-beta cutoffs
+    pub cutoffs: u64,
+}
+
+impl Stats {
+    /// Average number of children per expanded node.
+    pub fn branching(&self) -> f64 {
+        if self.expanded == 0 {
+            return 0.0;
+        }
+        self.nodes as f64 / self.expanded as f64
+    }
+
+    pub fn add(&mut self, other: &Stats) {
+        self.nodes += other.nodes;
+        self.expanded += other.expanded;
+        self.tt_hits += other.tt_hits;
+        self.cutoffs += other.cutoffs;
+    }
+}
+
+/// What the search decided and why.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub movement: Movement,
+    pub candidates: Vec<Candidate>,
+    pub depth: u8,
+    pub stats: Stats,
+}
+
+impl Search {
+    /// The line expected after the chosen move.
+    pub fn pv(&self) -> &[Ply] {
+        self.candidates
+            .iter()
+            .find(|c| c.movement == self.movement)
+            .map_or(&[], |c| &c.pv)
+    }
+}
+
+pub fn get_move(turn: Turn, weights: Weights) -> Move {
+    Move::new(search(turn, weights).movement)
+}
 
-    let mut state = State {
-        snake: turn.you.body.into(),
-        food: turn.board.food,
-        other_snakes: turn
-            .board
-            .snakes
-            .into_iter()
-            .filter(|s| s.id != turn.you.id)
-            .map(|s| s.body.into())
-            .collect(),
+/// Searches to the fixed `DEPTH` on one thread, the same answer however fast the machine is.
+/// Ties are broken the default way.
+pub fn search(turn: Turn, weights: Weights) -> Search {
+    let (mut state, determinizations) = prepare(turn, weights);
+    search_at::<DEPTH>(&mut state, &determinizations, 1, TieBreak::default())
+}
+
+/// Deepens the search two plies at a time, stopping before an iteration that would likely
+/// finish after `deadline`. Every finished iteration is published to `progress`, `None` if
+/// it was cancelled before the first one finished.
+///
+/// Root moves are split across `threads`, one thread searches them in order.
+pub fn search_until(
+    turn: Turn,
+    weights: Weights,
+    tie_break: TieBreak,
+    deadline: Instant,
+    threads: usize,
+    progress: &Progress,
+) -> Option<Search> {
+    let (mut state, determinizations) = prepare(turn, weights);
+    state.cancelled = progress.cancelled.clone();
+
+    let mut best: Option<Search> = None;
+    let mut last: Option<Duration> = None;
+
+    for depth in (2..=MAX_DEPTH).step_by(2) {
+        if let (Some(best), Some(last)) = (&best, last) {
+            // Two more plies multiply the work by about the branching factor squared
+            let branching = best.stats.branching().max(1.0);
+            let predicted = last.mul_f64(branching * branching);
+            if Instant::now() + predicted > deadline {
+                break;
+            }
+        }
+
+        let started = Instant::now();
+        let search = search_depth(&mut state, &determinizations, depth, threads, tie_break);
+        if progress.is_cancelled() {
+            break;
+        }
+        last = Some(started.elapsed());
+
+        *progress.best.lock().unwrap() = Some(search.clone());
+        best = Some(search);
+    }
+
+    best
+}
+
+/// Deepens to `depth` on one thread the way `search_until` got there, so a move it answered
+/// can be searched again exactly. Depth 0 is the fallback it answers with when out of time.
+pub fn search_to(turn: Turn, weights: Weights, tie_break: TieBreak, depth: u8) -> Search {
+    if depth == 0 {
+        return fallback(turn);
+    }
+
+    let (mut state, determinizations) = prepare(turn, weights);
+    let mut search = None;
+    // The table carries over between iterations, so the shallower ones change the answer
+    for depth in (2..=depth.min(MAX_DEPTH)).step_by(2) {
+        search = Some(search_depth(
+            &mut state,
+            &determinizations,
+            depth,
+            1,
+            tie_break,
+        ));
+    }
+
+    search.expect("searched at least once")
+}
+
+/// Answer for when the search has nothing in time, any move that doesn't die right away.
+pub fn fallback(turn: Turn) -> Search {
+    unsearched(safe_moves(turn).first().copied().unwrap_or(Movement::Up))
+}
+
+/// A move picked without searching.
+pub fn unsearched(movement: Movement) -> Search {
+    Search {
+        movement,
+        candidates: Vec::new(),
+        depth: 0,
+        stats: Stats::default(),
+    }
+}
+
+// Food is sampled for the deepest search whatever depth is asked for, so every depth
+// searches the same samples and replays draw what the server drew
+fn prepare(turn: Turn, weights: Weights) -> (State, Vec<FoodSpawns>) {
+    let settings = turn.game.ruleset.settings;
+    let samples = if FoodSpawns::spawns_food(&settings) {
+        FOOD_SAMPLES
+    } else {
+        1
     };
+    let mut rng = rng(&turn);
+
+    let mut state = State::from_turn(turn, weights);
+    debug!("\n{}", Grid::from_state(&state));
+
+    let determinizations = (0..samples)
+        .map(|_| {
+            FoodSpawns::sample(
+                &mut rng,
+                &settings,
+                MAX_DEPTH as usize / 2 + 1,
+                state.width,
+                state.height,
+            )
+        })
+        .collect();
+    // Drawn after the food so random ties leave the samples as they were
+    state.tie_seed = rng.gen();
+
+    (state, determinizations)
+}
+
+fn search_depth(
+    state: &mut State,
+    determinizations: &[FoodSpawns],
+    depth: u8,
+    threads: usize,
+    tie_break: TieBreak,
+) -> Search {
+    match depth {
+        2 => search_at::<2>(state, determinizations, threads, tie_break),
+        4 => search_at::<4>(state, determinizations, threads, tie_break),
+        6 => search_at::<6>(state, determinizations, threads, tie_break),
+        8 => search_at::<8>(state, determinizations, threads, tie_break),
+        10 => search_at::<10>(state, determinizations, threads, tie_break),
+        12 => search_at::<12>(state, determinizations, threads, tie_break),
+        _ => unreachable!("no search instantiated for depth {}", depth),
+    }
+}
+
+fn search_at<const DEPTH: u8>(
+    state: &mut State,
+    determinizations: &[FoodSpawns],
+    threads: usize,
+    tie_break: TieBreak,
+) -> Search {
     //      x+1  x-1  y+1  y-1
     // x+1  .... .... .... ....
     // x-1  .... .... .... ....
     // y+1  .yx. .xy. ..x. ..y.
     // y-1  .... .... ..y. ..x.
 
-    let possibilities = get_moves::<WIDTH, HEIGHT>(&state.snake, &state);
-
-    let mut max_score = isize::MIN;
-    let mut max = None;
-
-    for movement in possibilities
+    let moves: Vec<Movement> = get_moves(&state.snake, state)
         .iter()
         .enumerate()
         .filter(|(_, p)| **p)
         .map(|(i, _)| i.into())
-    {
-        debug!(?state.snake, ?movement);
-        let head = movement_to_move(state.snake.get_head(), movement);
-        let old_head = state.snake.push_head(head);
-
-        let score = minimax::<DEPTH, WIDTH, HEIGHT>(&mut state);
-        info!(?score, ?movement);
-        if max.is_none() || score > max_score {
-            max_score = score;
-            max = Some(movement);
-        }
+        .collect();
+
+    let scores = if threads <= 1 || moves.len() <= 1 {
+        moves
+            .iter()
+            .map(|&movement| score_root::<DEPTH>(state, movement, determinizations))
+            .collect()
+    } else {
+        score_parallel::<DEPTH>(state, &moves, determinizations, threads)
+    };
+
+    let mut candidates = Vec::with_capacity(4);
 
-        state.snake.pop_head(old_head);
-        debug!(?state.snake, ?movement);
+    // Candidates keep the move order whichever thread finished first
+    for (&movement, (score, pv)) in moves.iter().zip(scores) {
+        info!(?score, ?movement, pv = line(&pv));
+        candidates.push(Candidate {
+            movement,
+            score,
+            pv,
+        });
     }
 
-    match max {
-        Some(movement) => Move::new(movement),
-        None => {
+    let max_score = candidates.iter().map(|c| c.score).max();
+    let tied: Vec<Movement> = candidates
+        .iter()
+        .filter(|c| Some(c.score) == max_score)
+        .map(|c| c.movement)
+        .collect();
+
+    let movement = match tied.len() {
+        0 => {
             error!("Found no best move!");
-            return Move::new(Movement::Up);
+            Movement::Up
+        }
+        1 => tied[0],
+        _ => {
+            let movement = tie::pick(state, tie_break, &tied);
+            debug!(?tied, ?movement, "broke tie");
+            movement
+        }
+    };
+
+    Search {
+        movement,
+        candidates,
+        depth: DEPTH,
+        stats: state.stats,
+    }
+}
+
+/// Scores one root move averaged over the food determinizations, leaving `state` as it was.
+/// The line is the one expected under the first determinization.
+fn score_root<const DEPTH: u8>(
+    state: &mut State,
+    movement: Movement,
+    determinizations: &[FoodSpawns],
+) -> (isize, Vec<Ply>) {
+    debug!(?state.snake, ?movement);
+    let head = movement_to_move(state.snake.get_head(), movement);
+    let old_head = state.snake.push_head(head);
+    let health = state.step_health(head);
+
+    let mut values = Vec::with_capacity(determinizations.len());
+    let mut pv = vec![Ply::You(movement)];
+    for (sample, spawns) in determinizations.iter().enumerate() {
+        state.food_spawns = spawns.clone();
+        state.sample = sample as u8;
+        values.push(minimax::<DEPTH>(state));
+        if sample == 0 {
+            pv.extend_from_slice(&state.pv[0]);
         }
     }
+
+    state.health = health;
+    state.snake.pop_head(old_head);
+    debug!(?state.snake, ?movement);
+
+    (combine(&values), pv)
+}
+
+/// One root score from the scores of each food sample. Only a move won or lost in every
+/// sample scores `isize::MAX` or `isize::MIN`. Otherwise a loss in any sample outweighs
+/// every win, a win every evaluation, and the undecided samples are averaged.
+fn combine(values: &[isize]) -> isize {
+    let decided = |score: isize| values.iter().filter(|&&v| v == score).count() as i128;
+    let (wins, losses) = (decided(isize::MAX), decided(isize::MIN));
+    let samples = values.len() as i128;
+    if wins == samples {
+        return isize::MAX;
+    }
+    if losses == samples {
+        return isize::MIN;
+    }
+
+    let undecided = samples - wins - losses;
+    let average = if undecided == 0 {
+        0
+    } else {
+        let sum: i128 = values
+            .iter()
+            .filter(|&&v| v != isize::MAX && v != isize::MIN)
+            .map(|&v| v as i128)
+            .sum();
+        sum / undecided
+    };
+
+    // Fewest losses first, then most wins, each count a band wider than any average
+    let rank = wins - losses * (samples + 1);
+    let band = isize::MAX as i128 / ((samples + 1) * (samples + 1) + 1);
+    (rank * band + average.clamp(1 - band, band - 1)) as isize
+}
+
+/// Scores root moves on up to `threads` threads, each with its own copy of `state`. Scores
+/// come back in the order of `moves`.
+fn score_parallel<const DEPTH: u8>(
+    state: &mut State,
+    moves: &[Movement],
+    determinizations: &[FoodSpawns],
+    threads: usize,
+) -> Vec<(isize, Vec<Ply>)> {
+    let next = AtomicUsize::new(0);
+    let scores = Mutex::new(vec![(0, Vec::new()); moves.len()]);
+
+    let stats: Vec<Stats> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(moves.len()))
+            .map(|_| {
+                let mut state = state.clone();
+                state.stats = Stats::default();
+                let (next, scores) = (&next, &scores);
+
+                scope.spawn(move || {
+                    // Whoever is free takes the next move, root moves differ a lot in cost
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&movement) = moves.get(i) else {
+                            return state.stats;
+                        };
+                        let scored = score_root::<DEPTH>(&mut state, movement, determinizations);
+                        scores.lock().unwrap()[i] = scored;
+                    }
+                })
+            })
+            .collect();
+
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    for worker in &stats {
+        state.stats.add(worker);
+    }
+
+    scores.into_inner().unwrap()
 }
@@ -2,16 +2,16 @@ use crate::{requests::Point, responses::Movement, snake::Snake};
 
 use super::State;
 
-pub fn can_move<const WIDTH: i32, const HEIGHT: i32>(p: Point, state: &State) -> bool {
-    p.x >= 0 && p.x < WIDTH && p.y >= 0 && p.y < HEIGHT && !state.collides_with(p)
+pub fn can_move(p: Point, state: &State) -> bool {
+    state.in_bounds(p) && !state.collides_with(p)
 }
 
-pub fn get_moves<const WIDTH: i32, const HEIGHT: i32>(snake: &Snake, state: &State) -> [bool; 4] {
+pub fn get_moves(snake: &Snake, state: &State) -> [bool; 4] {
     [
-        can_move::<WIDTH, HEIGHT>(movement_to_move(snake.get_head(), Movement::Right), state),
-        can_move::<WIDTH, HEIGHT>(movement_to_move(snake.get_head(), Movement::Left), state),
-        can_move::<WIDTH, HEIGHT>(movement_to_move(snake.get_head(), Movement::Up), state),
-        can_move::<WIDTH, HEIGHT>(movement_to_move(snake.get_head(), Movement::Down), state),
+        can_move(movement_to_move(snake.get_head(), Movement::Right), state),
+        can_move(movement_to_move(snake.get_head(), Movement::Left), state),
+        can_move(movement_to_move(snake.get_head(), Movement::Up), state),
+        can_move(movement_to_move(snake.get_head(), Movement::Down), state),
     ]
 }
 
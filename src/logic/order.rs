@@ -0,0 +1,138 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::{requests::Point, responses::Movement};
+
+// Refutations remembered per ply
+const KILLERS: usize = 2;
+
+/// Which moves refuted positions so far in a search, to try them first elsewhere.
+///
+/// A move is what everyone moving at a node does: our one move when maximizing, every
+/// other snake's move at once when minimizing.
+#[derive(Clone, Default)]
+pub(crate) struct MoveOrder {
+    /// Latest moves that caused a cutoff, per ply
+    killers: Vec<Vec<u32>>,
+    /// Cutoffs weighted by remaining depth, by side, where the snake was and where it went
+    history: HashMap<(bool, Point, Movement), u64>,
+}
+
+impl MoveOrder {
+    /// Puts `moves` in the order to search them: the best move stored for the position,
+    /// killers at this ply, then by history. Ties keep their order.
+    pub fn sort(
+        &self,
+        moves: &mut [Vec<Movement>],
+        heads: &[Point],
+        maximize: bool,
+        ply: u8,
+        best: Option<u32>,
+    ) {
+        let killers = self
+            .killers
+            .get(ply as usize)
+            .map_or(&[][..], Vec::as_slice);
+
+        moves.sort_by_cached_key(|movements| {
+            let packed = pack(movements);
+            let killer = killers.iter().position(|&k| k == packed);
+            let history: u64 = heads
+                .iter()
+                .zip(movements)
+                .map(|(&head, &movement)| self.history(maximize, head, movement))
+                .sum();
+
+            (
+                Reverse(best == Some(packed)),
+                killer.unwrap_or(KILLERS),
+                Reverse(history),
+            )
+        });
+    }
+
+    /// Remembers `movements` refuting a position `remaining` plies from the horizon.
+    pub fn cutoff(
+        &mut self,
+        movements: &[Movement],
+        heads: &[Point],
+        maximize: bool,
+        ply: u8,
+        remaining: u8,
+    ) {
+        let ply = ply as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, Vec::new());
+        }
+
+        let packed = pack(movements);
+        let killers = &mut self.killers[ply];
+        if killers.first() != Some(&packed) {
+            killers.retain(|&k| k != packed);
+            killers.insert(0, packed);
+            killers.truncate(KILLERS);
+        }
+
+        // Cutoffs near the root save the most work
+        let weight = remaining as u64 * remaining as u64;
+        for (&head, &movement) in heads.iter().zip(movements) {
+            *self.history.entry((maximize, head, movement)).or_default() += weight;
+        }
+    }
+
+    fn history(&self, maximize: bool, head: Point, movement: Movement) -> u64 {
+        self.history
+            .get(&(maximize, head, movement))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Two bits per snake, enough to tell apart the moves at one node.
+pub(crate) fn pack(movements: &[Movement]) -> u32 {
+    movements
+        .iter()
+        .fold(0, |packed, &movement| packed << 2 | movement as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Movement::*;
+
+    const HEAD: Point = Point { x: 1, y: 1 };
+
+    fn sorted(order: &MoveOrder, best: Option<u32>) -> Vec<Vec<Movement>> {
+        let mut moves = vec![vec![Right], vec![Left], vec![Up], vec![Down]];
+        order.sort(&mut moves, &[HEAD], true, 3, best);
+        moves
+    }
+
+    #[test]
+    fn best_then_killers_then_history() {
+        let mut order = MoveOrder::default();
+        assert_eq!(
+            sorted(&order, None),
+            [vec![Right], vec![Left], vec![Up], vec![Down]]
+        );
+
+        // Cutoffs elsewhere only count through history
+        order.cutoff(&[Down], &[HEAD], true, 5, 4);
+        assert_eq!(sorted(&order, None)[0], [Down]);
+
+        order.cutoff(&[Up], &[HEAD], true, 3, 1);
+        order.cutoff(&[Left], &[HEAD], true, 3, 1);
+        assert_eq!(
+            sorted(&order, None),
+            [vec![Left], vec![Up], vec![Down], vec![Right]]
+        );
+
+        assert_eq!(sorted(&order, Some(pack(&[Right])))[0], [Right]);
+    }
+
+    #[test]
+    fn packs_every_snake() {
+        assert_ne!(pack(&[Right, Left]), pack(&[Left, Right]));
+        assert_ne!(pack(&[Up, Down]), pack(&[Down, Up]));
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::OnceLock;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::requests::Turn;
+
+// Stands in for the game id in every seed once set
+static FORCED: OnceLock<u64> = OnceLock::new();
+
+/// Seeds every search from `seed` instead of the game id, so the same turn of any game
+/// draws the same numbers. Only the first call counts, false for the ones after it.
+pub fn force_seed(seed: u64) -> bool {
+    FORCED.set(seed).is_ok()
+}
+
+/// Random numbers for anything stochastic about `turn`. The same snake on the same turn of
+/// the same game always draws the same ones, on any thread and in any build: unlike
+/// `StdRng`, ChaCha8's output is fixed across `rand` releases, so old logs still replay.
+pub fn rng(turn: &Turn) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed(turn, FORCED.get().copied()))
+}
+
+fn seed(turn: &Turn, forced: Option<u64>) -> u64 {
+    let game = forced.unwrap_or_else(|| fnv(turn.game.id.as_bytes()));
+    // Snakes searching the same turn shouldn't all sample the same food
+    mix(mix(game ^ fnv(turn.you.id.as_bytes())) ^ turn.turn as u64)
+}
+
+// FNV-1a, unlike `DefaultHasher` it is fixed across Rust versions
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// SplitMix64's finalizer, so neighbouring turns get unrelated seeds
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse;
+
+    use super::*;
+
+    #[test]
+    fn seeded_by_game_turn_and_snake() {
+        let turn = parse("...\n.A.\n.a.\nB..").unwrap();
+        assert_eq!(seed(&turn, None), seed(&turn.clone(), None));
+
+        let mut next = turn.clone();
+        next.turn += 1;
+        assert_ne!(seed(&turn, None), seed(&next, None));
+
+        let mut other_game = turn.clone();
+        other_game.game.id = "another".to_string();
+        assert_ne!(seed(&turn, None), seed(&other_game, None));
+        // Forcing the seed makes the game id irrelevant
+        assert_eq!(seed(&turn, Some(7)), seed(&other_game, Some(7)));
+        assert_ne!(seed(&turn, Some(7)), seed(&turn, Some(8)));
+
+        let mut other_snake = turn.clone();
+        other_snake.you.id = "snake-b".to_string();
+        assert_ne!(seed(&turn, None), seed(&other_snake, None));
+    }
+}
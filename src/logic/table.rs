@@ -0,0 +1,93 @@
+use std::{collections::HashMap, sync::Mutex};
+
+// Locks are per shard so the root threads rarely wait on each other
+const SHARDS: usize = 64;
+// A full shard is cleared, old positions matter least once the search moved on
+const SHARD_CAPACITY: usize = 1 << 12;
+
+/// How a stored value relates to the position's true score.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(crate) enum Bound {
+    Exact,
+    /// The search was cut off, the score is at least this
+    Lower,
+    /// No move reached the window, the score is at most this
+    Upper,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(crate) struct Entry {
+    /// Plies searched below the position
+    pub remaining: u8,
+    pub value: isize,
+    pub bound: Bound,
+    /// The move found best, packed, tried first when the position comes up again
+    pub best: Option<u32>,
+}
+
+/// Positions already searched, shared by every thread of one search and kept across its
+/// iterations.
+///
+/// Values are only used at the same remaining depth and within their bounds, so whichever
+/// thread stores one first the search scores the same.
+pub(crate) struct Table {
+    shards: Vec<Mutex<HashMap<u64, Entry>>>,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+}
+
+impl Table {
+    pub fn get(&self, key: u64) -> Option<Entry> {
+        self.shard(key).lock().unwrap().get(&key).copied()
+    }
+
+    pub fn insert(&self, key: u64, entry: Entry) {
+        let mut shard = self.shard(key).lock().unwrap();
+        if shard.len() >= SHARD_CAPACITY {
+            shard.clear();
+        }
+        shard.insert(key, entry);
+    }
+
+    fn shard(&self, key: u64) -> &Mutex<HashMap<u64, Entry>> {
+        &self.shards[key as usize % SHARDS]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(value: isize) -> Entry {
+        Entry {
+            remaining: 2,
+            value,
+            bound: Bound::Exact,
+            best: None,
+        }
+    }
+
+    #[test]
+    fn full_shards_are_cleared() {
+        let table = Table::default();
+        table.insert(3, entry(-7));
+        assert_eq!(table.get(3), Some(entry(-7)));
+        assert_eq!(table.get(4), None);
+
+        for i in 1..=SHARD_CAPACITY as u64 {
+            table.insert(3 + i * SHARDS as u64, entry(0));
+        }
+
+        assert_eq!(table.get(3), None);
+        assert_eq!(
+            table.get(3 + SHARD_CAPACITY as u64 * SHARDS as u64),
+            Some(entry(0))
+        );
+    }
+}
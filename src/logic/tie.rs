@@ -0,0 +1,126 @@
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{logic::eval::reachable, logic::moves::movement_to_move, responses::Movement};
+
+use super::State;
+
+/// How to choose among root moves the search scored the same.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TieBreak {
+    /// The first in right, left, up, down order
+    First,
+    /// The most cells reachable after the move, then the closest to the centre
+    #[default]
+    Space,
+    /// The closest to the centre of the board
+    Centre,
+    /// Any of them, drawn from the turn's seed
+    Random,
+}
+
+/// Picks one of `tied`, which must not be empty. Moves still tied after the policy keep
+/// their order.
+pub(crate) fn pick(state: &mut State, tie_break: TieBreak, tied: &[Movement]) -> Movement {
+    match tie_break {
+        TieBreak::First => tied[0],
+        TieBreak::Space => *tied
+            .iter()
+            .min_by_key(|&&movement| {
+                let space = space_after(state, movement);
+                (std::cmp::Reverse(space), from_centre(state, movement))
+            })
+            .unwrap(),
+        TieBreak::Centre => *tied
+            .iter()
+            .min_by_key(|&&movement| from_centre(state, movement))
+            .unwrap(),
+        TieBreak::Random => *tied
+            .choose(&mut ChaCha8Rng::seed_from_u64(state.tie_seed))
+            .unwrap(),
+    }
+}
+
+fn space_after(state: &mut State, movement: Movement) -> usize {
+    let head = movement_to_move(state.snake.get_head(), movement);
+    let old_head = state.snake.push_head(head);
+    let space = reachable(state, head);
+    state.snake.pop_head(old_head);
+    space
+}
+
+// Doubled, so even boards have a whole centre
+fn from_centre(state: &State, movement: Movement) -> i32 {
+    let head = movement_to_move(state.snake.get_head(), movement);
+    (2 * head.x - (state.width - 1)).abs() + (2 * head.y - (state.height - 1)).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{logic::Weights, parse::parse};
+
+    use super::*;
+
+    use Movement::*;
+
+    fn board(diagram: &str) -> State {
+        State::from_turn(parse(diagram).unwrap(), Weights::default())
+    }
+
+    #[test]
+    fn first_keeps_move_order() {
+        let mut state = board(".....\n.....\n..A..\n..a..\n.....");
+        assert_eq!(pick(&mut state, TieBreak::First, &[Right, Left, Up]), Right);
+        assert_eq!(pick(&mut state, TieBreak::First, &[Up, Left]), Up);
+    }
+
+    #[test]
+    fn centre_leaves_the_walls() {
+        let mut state = board(".....\n.....\na....\nA....\n.....");
+        assert_eq!(pick(&mut state, TieBreak::Centre, &[Down, Right]), Right);
+
+        // Equally far from the centre, the first one stays
+        let mut state = board(".....\n.....\n.aA..\n.....\n.....");
+        assert_eq!(pick(&mut state, TieBreak::Centre, &[Up, Down]), Up);
+        assert_eq!(pick(&mut state, TieBreak::Centre, &[Down, Up]), Down);
+    }
+
+    #[test]
+    fn space_avoids_pockets() {
+        // Going left is a dead end of two cells, stacked tails keep it closed
+        let diagram = "
+            .....
+            bbB..
+            ..A..
+            aaa..
+            a....
+            A length 6
+            B length 4";
+        let mut state = board(diagram);
+        assert_eq!(pick(&mut state, TieBreak::Space, &[Left, Right]), Right);
+
+        // Both sides open, the one nearer the centre wins
+        let mut state = board(".A...\n.a...\n.....\n.....\n.....");
+        assert_eq!(pick(&mut state, TieBreak::Space, &[Left, Right]), Right);
+    }
+
+    #[test]
+    fn random_follows_the_seed() {
+        let mut state = board(".....\n.....\n..A..\n..a..\n.....");
+        let tied = [Right, Left, Up];
+
+        let picks: Vec<_> = (0..20)
+            .map(|seed| {
+                state.tie_seed = seed;
+                let movement = pick(&mut state, TieBreak::Random, &tied);
+                assert_eq!(pick(&mut state, TieBreak::Random, &tied), movement);
+                movement
+            })
+            .collect();
+
+        assert!(picks.iter().all(|m| tied.contains(m)));
+        assert!(picks.iter().any(|&m| m != picks[0]));
+    }
+}
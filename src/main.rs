@@ -1,35 +1,229 @@
+mod analyze;
+mod arena;
+mod bench;
+mod config;
+mod deadline;
+mod engine;
+mod gamelog;
 mod logic;
+mod metrics;
+mod outcome;
+mod parse;
+mod pool;
+#[cfg(test)]
+mod puzzles;
+mod render;
+mod replay;
 #[allow(dead_code)]
 mod requests;
 #[allow(dead_code)]
 mod responses;
+mod shout;
 mod snake;
+mod strategy;
+mod tournament;
+mod tune;
 
 use std::{
+    collections::{BTreeMap, HashMap},
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
-use tracing::{info, warn};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
 
 #[derive(Clone)]
 struct AppState {
     hist: Arc<Mutex<hdrhistogram::Histogram<u64>>>,
+    weights: Arc<Mutex<logic::WeightsFile>>,
+    log: Option<Arc<gamelog::GameLog>>,
+    /// Search work per game, summarized at `/end`
+    stats: Arc<Mutex<HashMap<String, GameStats>>>,
+    metrics: Arc<Mutex<metrics::Metrics>>,
+    /// Last request and answer per game, to tell how we died at `/end`
+    last_moves: Arc<Mutex<HashMap<String, (requests::Turn, responses::Movement)>>>,
+    results: Option<Arc<Mutex<outcome::Stats>>>,
+    /// Game log and results writes, run in order off the request handlers
+    writes: mpsc::UnboundedSender<Write>,
+    /// Network overhead per game, to budget the search within the timeout
+    latency: Arc<Mutex<HashMap<String, deadline::Latency>>>,
+    /// What each game has been shouted at
+    shouters: Arc<Mutex<HashMap<String, shout::Shouter>>>,
+    /// When each game last sent a request, to forget the ones that never sent `/end`
+    seen: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Threads the searches run on
+    pool: Arc<pool::Pool>,
+    /// Searches running per game
+    games: Arc<pool::Games>,
+    config: Arc<config::Config>,
+    /// Answers moves instead of the deadline search unless it is minimax
+    strategy: Option<Arc<dyn strategy::Strategy>>,
+    /// Strategy spec the results are recorded under
+    spec: String,
 }
 
+type Write = Box<dyn FnOnce() + Send>;
+
+/// Games silent for this long are taken to be over without an `/end`.
+const STALE: Duration = Duration::from_secs(10 * 60);
+
+/// Search work summed over the moves of one game.
+#[derive(Default)]
+struct GameStats {
+    moves: u64,
+    depth: u64,
+    /// Time spent on this game's moves, in microseconds
+    micros: u64,
+    search: logic::Stats,
+}
+
+const USAGE: &str = "\
+battle_mulle [COMMAND] [FLAGS]
+
+commands:
+  serve       answer games over HTTP, the default
+  analyze     search a single turn and explain the move
+  arena       play local games between strategies
+  tournament  play every pairing of strategies in the arena
+  replay      search a logged game again and report changed moves
+  bench       time the search on fixed positions
+  tune        tune evaluation weights by self-play";
+
 #[tokio::main]
 async fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    // Flags alone are for serve
+    let command = match args.peek() {
+        Some(arg) if !arg.starts_with("--") || arg == "--help" => args.next(),
+        _ => None,
+    };
+
+    match command.as_deref() {
+        None | Some("serve") => {
+            let options = parse_or_exit(
+                config::Options::parse(args),
+                "battle_mulle serve [--config FILE] [--bind ADDR] [--strategy STRATEGY[:WEIGHTS]] \
+                 [--weights FILE] [--log-dir DIR] [--stats FILE] [--seed N]",
+            );
+            let config = or_exit(config::Config::load(&options));
+            serve(config).await;
+        }
+        Some("tune") => {
+            offline_tracing();
+            let options = parse_or_exit(
+                tune::Options::parse(args),
+                "battle_mulle tune [--iterations N] [--games N] [--max-turns N] [--seed N] [--from FILE] [--out FILE]",
+            );
+            or_exit(tune::run(&options));
+        }
+        Some("arena") => {
+            offline_tracing();
+            let options = parse_or_exit(
+                arena::Options::parse(args),
+                "battle_mulle arena --snake STRATEGY[:WEIGHTS] [--snake ...] [--width N] [--height N] \
+                 [--ruleset standard|royale|constrictor] [--minimum-food N] [--food-spawn-chance N] \
+                 [--hazard-damage N] [--shrink-every N] [--seed N] [--games N] [--max-turns N] [--log FILE]",
+            );
+            or_exit(arena::run(&options));
+        }
+        Some("replay") => {
+            offline_tracing();
+            let options = parse_or_exit(
+                replay::Options::parse(args),
+                "battle_mulle replay GAME.jsonl [--weights FILE] [--seed N]",
+            );
+            // Fail like a test would when decisions changed
+            if or_exit(replay::run(&options)) > 0 {
+                std::process::exit(1);
+            }
+        }
+        Some("analyze") => {
+            offline_tracing();
+            let options = parse_or_exit(
+                analyze::Options::parse(args),
+                "battle_mulle analyze TURN.json [--strategy STRATEGY[:WEIGHTS]] [--seed N]",
+            );
+            or_exit(analyze::run(&options));
+        }
+        Some("bench") => {
+            offline_tracing();
+            let options = parse_or_exit(
+                bench::Options::parse(args),
+                "battle_mulle bench [TURN.json ...] [--runs N] [--weights FILE]",
+            );
+            or_exit(bench::run(&options));
+        }
+        Some("tournament") => {
+            offline_tracing();
+            let options = parse_or_exit(
+                tournament::Options::parse(args),
+                "battle_mulle tournament --snake STRATEGY[:WEIGHTS] --snake ... [--games N] \
+                 [--threads N] [--json FILE] [arena flags]",
+            );
+            let report = or_exit(tournament::run(&options));
+            tournament::print(&report);
+        }
+        Some("help" | "--help") => println!("usage: {}", USAGE),
+        Some(other) => {
+            eprintln!("unknown command {}", other);
+            eprintln!("usage: {}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+async fn serve(config: config::Config) {
     tracing_subscriber::fmt::init();
 
+    if let Some(seed) = config.seed {
+        if logic::force_seed(seed) {
+            info!(seed, "searches seeded");
+        } else {
+            warn!(seed, "searches were already seeded, ignoring the seed");
+        }
+    }
+
+    let strategy = match config.strategy.as_str() {
+        "minimax" => None,
+        spec => Some(Arc::from(strategy::from_spec(spec).unwrap())),
+    };
+
+    let (writes, mut pending) = mpsc::unbounded_channel::<Write>();
+    let writer = tokio::spawn(async move {
+        while let Some(write) = pending.recv().await {
+            if let Err(e) = tokio::task::spawn_blocking(write).await {
+                error!("write failed: {}", e);
+            }
+        }
+    });
+
     let state = AppState {
         hist: Arc::new(Mutex::new(hdrhistogram::Histogram::new(4).unwrap())),
+        weights: Arc::new(Mutex::new(logic::WeightsFile::new(config.weights.clone()))),
+        log: game_log(&config).map(Arc::new),
+        stats: Arc::new(Mutex::new(HashMap::new())),
+        metrics: Arc::new(Mutex::new(metrics::Metrics::default())),
+        last_moves: Arc::new(Mutex::new(HashMap::new())),
+        results: results(&config).map(|r| Arc::new(Mutex::new(r))),
+        writes,
+        latency: Arc::new(Mutex::new(HashMap::new())),
+        shouters: Arc::new(Mutex::new(HashMap::new())),
+        seen: Arc::new(Mutex::new(HashMap::new())),
+        pool: Arc::new(pool::Pool::new(config.threads.search)),
+        games: Arc::new(pool::Games::new(config.threads.per_game)),
+        strategy,
+        spec: config.strategy_spec(),
+        config: Arc::new(config),
     };
+    let addr = state.config.bind.clone();
 
     // build our application with a route
     let app = Router::new()
@@ -37,35 +231,136 @@ async fn main() {
         .route("/start", post(start))
         .route("/end", post(end))
         .route("/move", post(movement))
+        .route("/metrics", get(metrics))
+        .route("/stats", get(stats))
         .with_state(state);
 
     // run it
 
-    let addr = std::env::var("SNEK").unwrap_or(String::from("127.0.0.1:3000"));
-
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     info!("listening on {}", listener.local_addr().unwrap());
     axum::serve(listener, app)
         .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
+
+    // The router is gone with its senders, let the last writes land
+    writer.await.unwrap();
 }
 
-async fn index() -> Json<responses::Info> {
+async fn index(State(state): State<AppState>) -> Json<responses::Info> {
+    let appearance = &state.config.appearance;
     Json(responses::Info {
         apiversion: "1".to_string(),
-        author: None,
-        color: Some("#b7410e".to_string()),
-        head: None,
-        tail: None,
-        version: Some("1".to_string()),
+        author: appearance.author.clone(),
+        color: Some(appearance.color.clone()),
+        head: appearance.head,
+        tail: appearance.tail,
+        version: appearance.version.clone(),
     })
 }
 
+fn game_log(config: &config::Config) -> Option<gamelog::GameLog> {
+    let dir = config.log_dir.clone()?;
+
+    match gamelog::GameLog::new(dir, config.log_keep) {
+        Ok(log) => Some(log),
+        Err(e) => {
+            error!("failed to open game log directory: {}", e);
+            None
+        }
+    }
+}
+
+fn results(config: &config::Config) -> Option<outcome::Stats> {
+    let path = config.stats.clone()?;
+
+    match outcome::Stats::load(path) {
+        Ok(stats) => Some(stats),
+        Err(e) => {
+            error!("failed to load game results: {}", e);
+            None
+        }
+    }
+}
+
+fn write(state: &AppState, write: impl FnOnce() + Send + 'static) {
+    // Only fails once the writer is gone at shutdown
+    let _ = state.writes.send(Box::new(write));
+}
+
+fn record(state: &AppState, entry: gamelog::Entry) {
+    if let Some(log) = state.log.clone() {
+        write(state, move || {
+            if let Err(e) = log.append(&entry) {
+                error!("failed to write game log: {}", e);
+            }
+        });
+    }
+}
+
+// Marks `game` as alive and forgets the games gone stale
+fn seen(state: &AppState, game: &str) {
+    let now = Instant::now();
+    let mut stale = Vec::new();
+    {
+        let mut seen = state.seen.lock().unwrap();
+        seen.insert(game.to_string(), now);
+        seen.retain(|id, at| {
+            let fresh = now.duration_since(*at) < STALE;
+            if !fresh {
+                stale.push(id.clone());
+            }
+            fresh
+        });
+    }
+
+    for game in stale {
+        warn!(game, "forgetting game that never ended");
+        forget(state, &game);
+    }
+}
+
+fn forget(state: &AppState, game: &str) {
+    state.seen.lock().unwrap().remove(game);
+    state.stats.lock().unwrap().remove(game);
+    state.latency.lock().unwrap().remove(game);
+    state.shouters.lock().unwrap().remove(game);
+    state.last_moves.lock().unwrap().remove(game);
+    state.games.end(game);
+}
+
 async fn start(State(state): State<AppState>, Json(req): Json<requests::Turn>) -> StatusCode {
     warn!(?req);
 
+    seen(&state, &req.game.id);
     state.hist.lock().unwrap().clear();
+    state
+        .stats
+        .lock()
+        .unwrap()
+        .insert(req.game.id.clone(), GameStats::default());
+    state.metrics.lock().unwrap().start();
+    // Pick up tuned weights between games without a restart
+    state.weights.lock().unwrap().reload();
+
+    let seed = state.config.seed;
+    let tie_break = state.config.tie_break;
+    record(
+        &state,
+        gamelog::Entry::Start {
+            request: req,
+            seed,
+            tie_break,
+        },
+    );
+    if let Some(log) = state.log.clone() {
+        write(&state, move || {
+            if let Err(e) = log.rotate() {
+                error!("failed to rotate game logs: {}", e);
+            }
+        });
+    }
 
     StatusCode::OK
 }
@@ -73,7 +368,30 @@ async fn start(State(state): State<AppState>, Json(req): Json<requests::Turn>) -
 async fn end(State(state): State<AppState>, Json(req): Json<requests::Turn>) -> StatusCode {
     warn!(?req);
 
-    state.hist.lock().unwrap().summarize();
+    {
+        let hist = state.hist.lock().unwrap();
+        hist.summarize();
+        if let Some(stats) = state.stats.lock().unwrap().remove(&req.game.id) {
+            stats.summarize();
+        }
+    }
+
+    let last = state.last_moves.lock().unwrap().remove(&req.game.id);
+    forget(&state, &req.game.id);
+    let result = outcome::detect(&req, last.as_ref().map(|(turn, m)| (turn, *m)));
+    warn!(?result.outcome, ?result.cause, turns = result.turns, "game over");
+
+    state.metrics.lock().unwrap().end(result.outcome);
+    if let Some(results) = state.results.clone() {
+        let spec = state.spec.clone();
+        write(&state, move || {
+            let mut results = results.lock().unwrap();
+            if let Err(e) = results.record(&spec, &result) {
+                error!("failed to write {}: {}", results.path().display(), e);
+            }
+        });
+    }
+    record(&state, gamelog::Entry::End { request: req });
 
     StatusCode::OK
 }
@@ -84,15 +402,168 @@ async fn movement(
 ) -> Json<responses::Move> {
     info!("Calc move!");
     let start = Instant::now();
+    seen(&state, &req.game.id);
     // tokio::time::sleep(Duration::from_millis(400)).await;
 
-    let snake_move = logic::get_move(req);
-    info!("move: {:?}", snake_move.movement);
+    let weights = state.weights.lock().unwrap().weights();
+    let request = req.clone();
+    let timeout = req.game.timeout;
+    let deadline = {
+        let mut latency = state.latency.lock().unwrap();
+        let latency = latency.entry(req.game.id.clone()).or_default();
+        latency.observe(req.you.latency);
+        latency.deadline(start, timeout, &state.config.timing)
+    };
+    info!(budget = ?deadline.saturating_duration_since(start), "searching");
 
-    let elapsed = start.elapsed().as_micros() as u64;
+    let progress = Arc::new(logic::Progress::default());
+    let task = {
+        let progress = progress.clone();
+        let (pool, games) = (state.pool.clone(), state.games.clone());
+        let threads = state.config.threads.root;
+        let tie_break = state.config.tie_break;
+        let strategy = state.strategy.clone();
+        async move {
+            // Held until the search is done, even if we stop waiting for it
+            let permit = games.acquire(&req.game.id).await;
+            pool.run(move || {
+                let search = match strategy {
+                    Some(strategy) => Some(
+                        strategy
+                            .search(req.clone())
+                            .unwrap_or_else(|| logic::unsearched(strategy.get_move(req).movement)),
+                    ),
+                    None => {
+                        logic::search_until(req, weights, tie_break, deadline, threads, &progress)
+                    }
+                };
+                drop(permit);
+                search
+            })
+            .await
+        }
+    };
+
+    // Answer with whatever is known at the deadline rather than let the engine pick for us
+    let search = match tokio::time::timeout_at(deadline.into(), task).await {
+        Ok(Ok(Some(search))) => search,
+        Ok(Ok(None)) => logic::fallback(request.clone()),
+        Ok(Err(_)) => {
+            error!("search panicked");
+            logic::fallback(request.clone())
+        }
+        Err(_) => {
+            progress.cancel();
+            state.metrics.lock().unwrap().cancel();
+            let best = progress.best();
+            warn!(
+                depth = best.as_ref().map(|s| s.depth),
+                "search cut off at deadline"
+            );
+            best.unwrap_or_else(|| logic::fallback(request.clone()))
+        }
+    };
+    info!(
+        depth = search.depth,
+        nodes = search.stats.nodes,
+        branching = format!("{:.2}", search.stats.branching()),
+        tt_hits = search.stats.tt_hits,
+        cutoffs = search.stats.cutoffs,
+        pv = logic::line(search.pv()),
+        candidates = ?search.candidates,
+        "move: {:?}",
+        search.movement
+    );
+
+    let elapsed = start.elapsed();
+    if let Some(latency) = state.latency.lock().unwrap().get_mut(&request.game.id) {
+        latency.answered(elapsed);
+    }
+
+    let elapsed = elapsed.as_micros() as u64;
     state.hist.lock().unwrap().record(elapsed).unwrap();
+    state
+        .stats
+        .lock()
+        .unwrap()
+        .entry(request.game.id.clone())
+        .or_default()
+        .record(&search, elapsed);
+    state
+        .metrics
+        .lock()
+        .unwrap()
+        .record_move(elapsed, timeout, &search);
+
+    let shout = state
+        .shouters
+        .lock()
+        .unwrap()
+        .entry(request.game.id.clone())
+        .or_default()
+        .shout(&state.config.shouts, &request, &search);
+    if let Some(shout) = &shout {
+        info!(shout, "shouting");
+    }
+
+    state
+        .last_moves
+        .lock()
+        .unwrap()
+        .insert(request.game.id.clone(), (request.clone(), search.movement));
+
+    if state.log.is_some() {
+        record(
+            &state,
+            gamelog::Entry::Move {
+                request,
+                chosen: search.movement,
+                candidates: search.candidates,
+                depth: search.depth,
+                stats: search.stats,
+                latency_us: elapsed,
+            },
+        );
+    }
+
+    Json(responses::Move::new(search.movement).with_shout(shout))
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.lock().unwrap().render(),
+    )
+}
+
+async fn stats(State(state): State<AppState>) -> Json<BTreeMap<String, outcome::Record>> {
+    Json(match &state.results {
+        Some(results) => results.lock().unwrap().records().clone(),
+        None => BTreeMap::new(),
+    })
+}
 
-    Json(snake_move)
+// The search logs every candidate, keep offline output readable
+fn offline_tracing() {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .init();
+}
+
+fn parse_or_exit<T>(options: Result<T, String>, usage: &str) -> T {
+    options.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("usage: {}", usage);
+        std::process::exit(2);
+    })
+}
+
+// A missing or broken file is the operator's mistake, not a bug worth a backtrace
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    })
 }
 
 trait Summary {
@@ -115,6 +586,33 @@ impl Summary for hdrhistogram::Histogram<u64> {
     }
 }
 
+impl GameStats {
+    fn record(&mut self, search: &logic::Search, micros: u64) {
+        self.moves += 1;
+        self.depth += search.depth as u64;
+        self.micros += micros;
+        self.search.add(&search.stats);
+    }
+
+    fn summarize(&self) {
+        let moves = self.moves.max(1);
+        let seconds = self.micros as f64 / 1_000_000.0;
+
+        info!("nodes searched: {}", self.search.nodes);
+        info!("nodes per move: {}", self.search.nodes / moves);
+        if seconds > 0.0 {
+            info!(
+                "nodes per second: {:.0}",
+                self.search.nodes as f64 / seconds
+            );
+        }
+        info!("mean depth: {:.1}", self.depth as f64 / moves as f64);
+        info!("branching factor: {:.2}", self.search.branching());
+        info!("tt hits: {}", self.search.tt_hits);
+        info!("cutoffs: {}", self.search.cutoffs);
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
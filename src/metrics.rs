@@ -0,0 +1,205 @@
+use std::fmt::Write;
+
+use hdrhistogram::Histogram;
+
+use crate::{logic::Search, outcome::Outcome};
+
+// Upper bounds of the exported latency buckets, in seconds
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.2, 0.3, 0.4, 0.5, 0.75, 1.0];
+
+/// Counters exported in the Prometheus text format on `/metrics`.
+///
+/// Unlike the per-game histogram in `AppState` these are never reset.
+pub struct Metrics {
+    latency: Histogram<u64>,
+    games_started: u64,
+    games_ended: u64,
+    wins: u64,
+    losses: u64,
+    draws: u64,
+    timeouts: u64,
+    cancelled: u64,
+    depth: u8,
+    nodes: u64,
+    search_seconds: f64,
+    nodes_per_second: f64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            // Anything past a minute is clamped
+            latency: Histogram::new_with_bounds(1, 60_000_000, 3).unwrap(),
+            games_started: 0,
+            games_ended: 0,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            timeouts: 0,
+            cancelled: 0,
+            depth: 0,
+            nodes: 0,
+            search_seconds: 0.0,
+            nodes_per_second: 0.0,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn start(&mut self) {
+        self.games_started += 1;
+    }
+
+    pub fn end(&mut self, outcome: Outcome) {
+        self.games_ended += 1;
+        match outcome {
+            Outcome::Won => self.wins += 1,
+            Outcome::Lost => self.losses += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+    }
+
+    /// Counts a search stopped at its deadline before it finished.
+    pub fn cancel(&mut self) {
+        self.cancelled += 1;
+    }
+
+    /// Records a move answered after `latency_us`, out of the game's `timeout_ms`.
+    pub fn record_move(&mut self, latency_us: u64, timeout_ms: i32, search: &Search) {
+        self.latency.saturating_record(latency_us);
+        if latency_us >= timeout_ms.max(0) as u64 * 1000 {
+            self.timeouts += 1;
+        }
+
+        let seconds = latency_us as f64 / 1_000_000.0;
+        self.depth = search.depth;
+        self.nodes += search.stats.nodes;
+        self.search_seconds += seconds;
+        if seconds > 0.0 {
+            self.nodes_per_second = search.stats.nodes as f64 / seconds;
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let counters = [
+            ("games_started_total", "Games started", self.games_started),
+            ("games_ended_total", "Games ended", self.games_ended),
+            ("games_won_total", "Games won", self.wins),
+            ("games_lost_total", "Games lost", self.losses),
+            ("games_drawn_total", "Games drawn", self.draws),
+            (
+                "move_timeouts_total",
+                "Moves answered after the game's timeout",
+                self.timeouts,
+            ),
+            (
+                "searches_cancelled_total",
+                "Searches cut off at the deadline",
+                self.cancelled,
+            ),
+            ("search_nodes_total", "Positions searched", self.nodes),
+        ];
+        for (name, help, value) in counters {
+            metric(&mut out, name, help, "counter", value);
+        }
+
+        metric(
+            &mut out,
+            "search_seconds_total",
+            "Time spent answering moves",
+            "counter",
+            self.search_seconds,
+        );
+        metric(
+            &mut out,
+            "search_depth",
+            "Depth of the last search",
+            "gauge",
+            self.depth,
+        );
+        metric(
+            &mut out,
+            "search_nodes_per_second",
+            "Search speed of the last move",
+            "gauge",
+            self.nodes_per_second,
+        );
+
+        let name = "battle_mulle_move_latency_seconds";
+        let _ = writeln!(out, "# HELP {} Time to answer /move", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for le in LATENCY_BUCKETS {
+            let count = self.latency.count_between(0, (le * 1_000_000.0) as u64);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, count);
+        }
+        let count = self.latency.len();
+        let sum = self.latency.mean() * count as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+
+        out
+    }
+}
+
+fn metric(out: &mut String, name: &str, help: &str, kind: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP battle_mulle_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE battle_mulle_{} {}", name, kind);
+    let _ = writeln!(out, "battle_mulle_{} {}", name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{logic::Stats, responses::Movement};
+
+    use super::*;
+
+    fn search(nodes: u64) -> Search {
+        Search {
+            movement: Movement::Up,
+            candidates: vec![],
+            depth: 6,
+            stats: Stats {
+                nodes,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn render_prometheus_text() {
+        let mut metrics = Metrics::default();
+        metrics.start();
+        metrics.record_move(20_000, 500, &search(1000));
+        metrics.record_move(600_000, 500, &search(3000));
+        metrics.cancel();
+        metrics.end(Outcome::Won);
+
+        let text = metrics.render();
+
+        for line in [
+            "# TYPE battle_mulle_games_started_total counter",
+            "battle_mulle_games_started_total 1",
+            "battle_mulle_games_won_total 1",
+            "battle_mulle_move_timeouts_total 1",
+            "battle_mulle_searches_cancelled_total 1",
+            "battle_mulle_search_nodes_total 4000",
+            "battle_mulle_search_depth 6",
+            "battle_mulle_search_nodes_per_second 5000",
+            "battle_mulle_move_latency_seconds_bucket{le=\"0.025\"} 1",
+            "battle_mulle_move_latency_seconds_bucket{le=\"0.5\"} 1",
+            "battle_mulle_move_latency_seconds_bucket{le=\"0.75\"} 2",
+            "battle_mulle_move_latency_seconds_bucket{le=\"+Inf\"} 2",
+            "battle_mulle_move_latency_seconds_count 2",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {}\n{}",
+                line,
+                text
+            );
+        }
+    }
+}
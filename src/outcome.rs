@@ -0,0 +1,285 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{next_head, Cause},
+    requests::Turn,
+    responses::Movement,
+};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Won,
+    Lost,
+    Draw,
+}
+
+/// How a game ended for us.
+#[derive(Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct GameResult {
+    pub outcome: Outcome,
+    /// Why we were eliminated, if we were and it can be told from the boards
+    pub cause: Option<Cause>,
+    pub turns: u32,
+}
+
+/// Works out the result from the final board sent to `/end` and, if we saw it, the last
+/// turn we moved on along with the move we made.
+pub fn detect(end: &Turn, last: Option<(&Turn, Movement)>) -> GameResult {
+    let alive = end.board.snakes.iter().any(|s| s.id == end.you.id);
+
+    let outcome = if alive {
+        if end.board.snakes.len() == 1 {
+            Outcome::Won
+        } else {
+            // Still several snakes on the board, the game was cut short
+            Outcome::Draw
+        }
+    } else if end.board.snakes.is_empty() {
+        Outcome::Draw
+    } else {
+        Outcome::Lost
+    };
+
+    GameResult {
+        outcome,
+        cause: if alive { None } else { cause(end, last) },
+        turns: end.turn,
+    }
+}
+
+fn cause(end: &Turn, last: Option<(&Turn, Movement)>) -> Option<Cause> {
+    let you = &end.you;
+    let (mut head, mut health, mut body) = (you.head, you.health, &you.body[1..]);
+
+    // Some engines send our snake as it was before the fatal move, replay it
+    if let Some((last, movement)) = last {
+        if last.you.head == head {
+            head = next_head(head, movement);
+            health = health.min(last.you.health - 1);
+            body = &you.body[..you.body.len() - 1];
+        }
+    }
+
+    if health <= 0 {
+        return Some(if end.board.hazards.contains(&head) {
+            Cause::Hazard
+        } else {
+            Cause::Starvation
+        });
+    }
+
+    if head.x < 0 || head.x >= end.board.width || head.y < 0 || head.y >= end.board.height {
+        return Some(Cause::Wall);
+    }
+
+    if body.contains(&head) {
+        return Some(Cause::SelfCollision);
+    }
+
+    let survivors: Vec<_> = end.board.snakes.iter().filter(|s| s.id != you.id).collect();
+
+    if survivors.iter().any(|s| s.body[1..].contains(&head)) {
+        return Some(Cause::BodyCollision);
+    }
+    if survivors.iter().any(|s| s.head == head) {
+        return Some(Cause::HeadToHead);
+    }
+
+    // Snakes that went down with us are only on the last board we saw
+    let (last, _) = last?;
+    let fallen: Vec<_> = last
+        .board
+        .snakes
+        .iter()
+        .filter(|s| s.id != you.id && survivors.iter().all(|o| o.id != s.id))
+        .collect();
+
+    // Everything but the tail is still there after they moved
+    if fallen
+        .iter()
+        .any(|s| s.body[..s.body.len() - 1].contains(&head))
+    {
+        return Some(Cause::BodyCollision);
+    }
+    // Off their body, our cell is one they could have moved onto as well
+    if fallen
+        .iter()
+        .any(|s| s.head.manhattan_distance(&head) == 1 && s.body.len() >= last.you.body.len())
+    {
+        return Some(Cause::HeadToHead);
+    }
+
+    None
+}
+
+/// Totals for one strategy across every game it played.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Record {
+    pub games: u64,
+    pub won: u64,
+    pub lost: u64,
+    pub drawn: u64,
+    pub turns: u64,
+    /// How often each cause ended our games
+    pub causes: BTreeMap<Cause, u64>,
+}
+
+impl Record {
+    pub fn add(&mut self, result: &GameResult) {
+        self.games += 1;
+        self.turns += result.turns as u64;
+        match result.outcome {
+            Outcome::Won => self.won += 1,
+            Outcome::Lost => self.lost += 1,
+            Outcome::Draw => self.drawn += 1,
+        }
+        if let Some(cause) = result.cause {
+            *self.causes.entry(cause).or_default() += 1;
+        }
+    }
+}
+
+/// Per-strategy records, written back to a JSON file after every game.
+pub struct Stats {
+    path: PathBuf,
+    records: BTreeMap<String, Record>,
+}
+
+impl Stats {
+    /// Loads earlier records from `path`, starting empty if there is no file yet.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let records = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self { path, records })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn records(&self) -> &BTreeMap<String, Record> {
+        &self.records
+    }
+
+    pub fn record(&mut self, strategy: &str, result: &GameResult) -> io::Result<()> {
+        self.records
+            .entry(strategy.to_string())
+            .or_default()
+            .add(result);
+
+        fs::write(&self.path, serde_json::to_string_pretty(&self.records)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse::parse, requests::Point};
+
+    use super::*;
+
+    fn lost(end: &Turn, last: Option<(&Turn, Movement)>) -> Option<Cause> {
+        let mut end = end.clone();
+        end.board.snakes.retain(|s| s.id != end.you.id);
+
+        let result = detect(&end, last);
+        assert_eq!(result.outcome, Outcome::Lost);
+        result.cause
+    }
+
+    fn move_head(turn: &mut Turn, head: Point) {
+        turn.you.body.insert(0, head);
+        turn.you.body.pop();
+        turn.you.head = head;
+    }
+
+    #[test]
+    fn outcomes() {
+        assert_eq!(detect(&parse("A..").unwrap(), None).outcome, Outcome::Won);
+        assert_eq!(detect(&parse("A.B").unwrap(), None).outcome, Outcome::Draw);
+
+        let mut turn = parse("A.B").unwrap();
+        turn.board.snakes.clear();
+        assert_eq!(detect(&turn, None).outcome, Outcome::Draw);
+    }
+
+    #[test]
+    fn causes() {
+        let mut starved = parse("Aa.\n..B\nA us health 0").unwrap();
+        assert_eq!(lost(&starved, None), Some(Cause::Starvation));
+        starved.board.hazards.push(starved.you.head);
+        assert_eq!(lost(&starved, None), Some(Cause::Hazard));
+
+        let mut wall = parse("Aa.\n..B").unwrap();
+        move_head(&mut wall, Point { x: -1, y: 1 });
+        assert_eq!(lost(&wall, None), Some(Cause::Wall));
+
+        let mut body = parse("aA.\n.Bb").unwrap();
+        move_head(&mut body, Point { x: 2, y: 0 });
+        assert_eq!(lost(&body, None), Some(Cause::BodyCollision));
+
+        let mut head = parse("aA.\n.Bb\nB length 3").unwrap();
+        move_head(&mut head, Point { x: 1, y: 0 });
+        assert_eq!(lost(&head, None), Some(Cause::HeadToHead));
+
+        let mut coiled = parse("aaa\naAa\n..B").unwrap();
+        move_head(&mut coiled, Point { x: 1, y: 2 });
+        assert_eq!(lost(&coiled, None), Some(Cause::SelfCollision));
+    }
+
+    #[test]
+    fn cause_from_last_turn() {
+        // We and B both went for the middle, the end board only has C left
+        let last = parse("A.B\n...\n..C").unwrap();
+        let mut end = last.clone();
+        end.board.snakes.retain(|s| s.name == "C");
+
+        let result = detect(&end, Some((&last, Movement::Right)));
+
+        assert_eq!(result.outcome, Outcome::Lost);
+        assert_eq!(result.cause, Some(Cause::HeadToHead));
+
+        // Next to B's head but into its neck, B died of something else
+        let last = parse("aA.\n.bB\n.bC").unwrap();
+        let mut end = last.clone();
+        end.board.snakes.retain(|s| s.name == "C");
+
+        let result = detect(&end, Some((&last, Movement::Down)));
+
+        assert_eq!(result.cause, Some(Cause::BodyCollision));
+    }
+
+    #[test]
+    fn records_persist() {
+        let path =
+            std::env::temp_dir().join(format!("battle_mulle-stats-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut stats = Stats::load(path.clone()).unwrap();
+        let result = GameResult {
+            outcome: Outcome::Lost,
+            cause: Some(Cause::Wall),
+            turns: 40,
+        };
+        stats.record("minimax", &result).unwrap();
+        stats.record("minimax", &result).unwrap();
+
+        let reloaded = Stats::load(path).unwrap();
+        let record = &reloaded.records()["minimax"];
+        assert_eq!(record.games, 2);
+        assert_eq!(record.lost, 2);
+        assert_eq!(record.turns, 80);
+        assert_eq!(record.causes[&Cause::Wall], 2);
+    }
+}
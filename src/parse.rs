@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+
+use crate::requests::{Board, Game, Point, Ruleset, Snake, Turn};
+
+const DEFAULT_HEALTH: i32 = 100;
+
+/// Parses a board drawn the way [`crate::render::Grid`] prints one back into a request.
+///
+/// Snake `A` is us and the others follow in letter order. Heads are upper case and bodies
+/// lower case, the body is traced outwards from the head. Food is `*`, hazards `~` and
+/// `.` is empty, move arrows are ignored. Row and column labels are optional, so are the
+/// spaces between cells. Lines after the grid annotate a snake, e.g. `B them health 40
+/// length 5`: words before `health`/`length` become its name, and a length longer than
+/// the drawn body stacks the tail.
+pub fn parse(diagram: &str) -> Result<Turn, String> {
+    let mut rows = Vec::new();
+    let mut annotations = BTreeMap::new();
+
+    for line in diagram.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let mut tokens: Vec<_> = line.split_whitespace().collect();
+        if tokens.iter().all(|t| t.parse::<u32>().is_ok()) {
+            // Column labels
+            continue;
+        }
+        if tokens.len() > 1 && tokens[0].parse::<u32>().is_ok() {
+            tokens.remove(0);
+        }
+
+        let cells: Vec<char> = if tokens.iter().all(|t| t.chars().count() == 1) {
+            tokens.iter().flat_map(|t| t.chars()).collect()
+        } else if tokens.len() == 1 {
+            tokens[0].chars().collect()
+        } else {
+            let annotation = Annotation::parse(&tokens)?;
+            annotations.insert(annotation.letter, annotation);
+            continue;
+        };
+
+        if let Some(c) = cells.iter().find(|c| !is_cell(**c)) {
+            return Err(format!("unexpected {:?} in row {:?}", c, line));
+        }
+        rows.push(cells);
+    }
+
+    let height = rows.len() as i32;
+    let width = rows.first().map_or(0, |r| r.len() as i32);
+    if width == 0 {
+        return Err("empty board".to_string());
+    }
+    if rows.iter().any(|r| r.len() as i32 != width) {
+        return Err("rows have different widths".to_string());
+    }
+
+    let at = |p: Point| rows[(height - 1 - p.y) as usize][p.x as usize];
+    let points = (0..height).flat_map(|y| (0..width).map(move |x| Point { x, y }));
+
+    let mut food = Vec::new();
+    let mut hazards = Vec::new();
+    let mut heads = BTreeMap::new();
+    for p in points.clone() {
+        match at(p) {
+            '*' => food.push(p),
+            '~' => hazards.push(p),
+            c if c.is_ascii_uppercase() && heads.insert(c, p).is_some() => {
+                return Err(format!("snake {} has two heads", c));
+            }
+            // A lone `v` is a move arrow
+            c if c.is_ascii_lowercase() && c != 'v' => {
+                let head = c.to_ascii_uppercase();
+                if !points.clone().any(|p| at(p) == head) {
+                    return Err(format!("snake {} has no head", head));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut snakes = Vec::new();
+    for (&letter, &head) in &heads {
+        let segments: Vec<_> = points
+            .clone()
+            .filter(|&p| at(p) == letter.to_ascii_lowercase())
+            .collect();
+        let mut body = vec![head];
+        if !trace(&mut body, &segments) {
+            return Err(format!("snake {}'s body isn't connected", letter));
+        }
+
+        let annotation = annotations.remove(&letter).unwrap_or_default();
+        if let Some(length) = annotation.length {
+            let tail = *body.last().unwrap();
+            body.resize(length.max(body.len()), tail);
+        }
+
+        snakes.push(Snake {
+            id: format!("snake-{}", letter.to_ascii_lowercase()),
+            name: annotation.name.unwrap_or_else(|| letter.to_string()),
+            health: annotation.health.unwrap_or(DEFAULT_HEALTH),
+            head,
+            length: body.len() as u32,
+            body,
+            shout: String::new(),
+            squad: String::new(),
+            latency: 0,
+        });
+    }
+
+    if let Some(letter) = annotations.keys().next() {
+        return Err(format!("annotation for missing snake {}", letter));
+    }
+    if heads.keys().next() != Some(&'A') {
+        return Err("no snake A to play as".to_string());
+    }
+
+    Ok(Turn {
+        game: Game {
+            id: "diagram".to_string(),
+            ruleset: Ruleset {
+                name: "standard".to_string(),
+                ..Default::default()
+            },
+            timeout: 500,
+        },
+        turn: 0,
+        board: Board {
+            height,
+            width,
+            food,
+            hazards,
+            snakes: snakes.clone(),
+        },
+        you: snakes[0].clone(),
+    })
+}
+
+fn is_cell(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '.' | '*' | '~' | '^' | '<' | '>')
+}
+
+/// Extends `body` through every segment, backtracking where the drawing branches.
+fn trace(body: &mut Vec<Point>, segments: &[Point]) -> bool {
+    if body.len() == segments.len() + 1 {
+        return true;
+    }
+
+    let last = *body.last().unwrap();
+    for &next in segments {
+        if last.manhattan_distance(&next) == 1 && !body.contains(&next) {
+            body.push(next);
+            if trace(body, segments) {
+                return true;
+            }
+            body.pop();
+        }
+    }
+
+    false
+}
+
+#[derive(Default)]
+struct Annotation {
+    letter: char,
+    name: Option<String>,
+    health: Option<i32>,
+    length: Option<usize>,
+}
+
+impl Annotation {
+    fn parse(tokens: &[&str]) -> Result<Self, String> {
+        let line = tokens.join(" ");
+        let mut chars = tokens[0].chars();
+        let letter = match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_uppercase() => c,
+            _ => return Err(format!("can't read {:?}", line)),
+        };
+
+        let mut annotation = Self {
+            letter,
+            ..Default::default()
+        };
+        let mut name = Vec::new();
+        let mut tokens = tokens[1..].iter();
+        while let Some(&token) = tokens.next() {
+            let mut value = || {
+                tokens
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| format!("missing {} in {:?}", token, line))
+            };
+            match token {
+                "health" => annotation.health = Some(value()?),
+                "length" => annotation.length = Some(value()? as usize),
+                _ if annotation.health.is_none() && annotation.length.is_none() => name.push(token),
+                _ => return Err(format!("unexpected {:?} in {:?}", token, line)),
+            }
+        }
+        if !name.is_empty() {
+            annotation.name = Some(name.join(" "));
+        }
+
+        Ok(annotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::Grid;
+
+    use super::*;
+
+    #[test]
+    fn parse_compact() {
+        let turn = parse(
+            "
+            ..*..
+            .Aa..
+            ..a.~
+            ..Bb.
+            B them health 40
+            ",
+        )
+        .unwrap();
+
+        assert_eq!((turn.board.width, turn.board.height), (5, 4));
+        assert_eq!(turn.board.food, vec![Point { x: 2, y: 3 }]);
+        assert_eq!(turn.board.hazards, vec![Point { x: 4, y: 1 }]);
+        assert_eq!(
+            turn.you.body,
+            vec![
+                Point { x: 1, y: 2 },
+                Point { x: 2, y: 2 },
+                Point { x: 2, y: 1 }
+            ]
+        );
+        assert_eq!(turn.you.health, DEFAULT_HEALTH);
+
+        let them = &turn.board.snakes[1];
+        assert_eq!(them.name, "them");
+        assert_eq!(them.health, 40);
+        assert_eq!(them.body, vec![Point { x: 2, y: 0 }, Point { x: 3, y: 0 }]);
+    }
+
+    #[test]
+    fn parse_branching_body() {
+        // Tracing from the head's right ends early, the body has to go up first
+        let turn = parse(
+            "
+            aa.
+            Aa.
+            .aa
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(turn.you.body.len(), 6);
+        assert_eq!(turn.you.body[1], Point { x: 0, y: 2 });
+        assert_eq!(*turn.you.body.last().unwrap(), Point { x: 2, y: 0 });
+    }
+
+    #[test]
+    fn round_trips_rendered_boards() {
+        let turn = parse(
+            "
+            ~.*.
+            ..B.
+            .Ab.
+            .a..
+            A us health 7 length 3
+            ",
+        )
+        .unwrap();
+
+        let rendered = Grid::from_turn(&turn).with_move(crate::responses::Movement::Left);
+        let reparsed = parse(&rendered.to_string()).unwrap();
+
+        assert_eq!(reparsed, turn);
+        // Stacked up to the annotated length
+        assert_eq!(turn.you.body[1], turn.you.body[2]);
+    }
+
+    #[test]
+    fn rejects_bad_boards() {
+        assert!(parse("").is_err());
+        assert!(parse("..B\nb..").is_err());
+        assert!(parse("A.\n...").is_err());
+        assert!(parse("Aa.\n..a").is_err());
+        assert!(parse("A?").is_err());
+        assert!(parse("Ab.").is_err());
+        assert!(parse("A..\nC health 3").is_err());
+    }
+}
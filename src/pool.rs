@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed number of threads searches run on, so they never block the async runtime.
+///
+/// At most one job per thread is handed over, the rest wait their turn in `run`.
+pub struct Pool {
+    jobs: mpsc::Sender<Job>,
+    slots: Arc<Semaphore>,
+}
+
+impl Pool {
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+
+        for i in 0..threads {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(format!("search-{}", i))
+                .spawn(move || loop {
+                    let job = match queue.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        // The pool is gone
+                        Err(_) => return,
+                    };
+                    // A panicking search loses its answer, not the thread
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                })
+                .expect("failed to spawn search thread");
+        }
+
+        Self {
+            jobs,
+            slots: Arc::new(Semaphore::new(threads)),
+        }
+    }
+
+    /// Runs `job` once a thread is free. Errors if it panicked.
+    ///
+    /// Dropping the future before a thread is free drops the job unrun, after that it runs
+    /// to completion regardless.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, oneshot::error::RecvError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let slot = self.slots.clone().acquire_owned().await.unwrap();
+        let (tx, rx) = oneshot::channel();
+
+        let job: Job = Box::new(move || {
+            let _ = tx.send(job());
+            drop(slot);
+        });
+        self.jobs.send(job).expect("search threads are gone");
+
+        rx.await
+    }
+}
+
+/// Caps how many searches a single game can have at once, so one game with overrunning
+/// moves can't take every thread from the others.
+pub struct Games {
+    limit: usize,
+    games: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl Games {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            games: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits for a free search slot in `game`, held until the permit is dropped.
+    pub async fn acquire(&self, game: &str) -> OwnedSemaphorePermit {
+        let slots = self
+            .games
+            .lock()
+            .unwrap()
+            .entry(game.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limit)))
+            .clone();
+
+        slots.acquire_owned().await.unwrap()
+    }
+
+    /// Forgets a game that ended, searches still running keep their permits.
+    pub fn end(&self, game: &str) {
+        self.games.lock().unwrap().remove(game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn bounded_by_threads() {
+        let pool = Arc::new(Pool::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+
+        let jobs: Vec<_> = (0..6)
+            .map(|i| {
+                let (pool, running, most) = (pool.clone(), running.clone(), most.clone());
+                tokio::spawn(async move {
+                    pool.run(move || {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        most.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(20));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        i * 2
+                    })
+                    .await
+                })
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for job in jobs {
+            results.push(job.await.unwrap().unwrap());
+        }
+
+        assert_eq!(results, vec![0, 2, 4, 6, 8, 10]);
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn survives_panics() {
+        let pool = Pool::new(1);
+
+        assert!(pool.run(|| panic!("search blew up")).await.is_err());
+        assert_eq!(pool.run(|| 7).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn limit_per_game() {
+        let games = Games::new(1);
+
+        let first = games.acquire("a").await;
+        // Other games aren't held up
+        let _other = games.acquire("b").await;
+
+        let second = tokio::time::timeout(Duration::from_millis(20), games.acquire("a"));
+        assert!(second.await.is_err());
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_millis(20), games.acquire("a"));
+        assert!(second.await.is_ok());
+    }
+}
@@ -0,0 +1,125 @@
+//! Tactical positions the search has to get right, one file each under `puzzles/`.
+//!
+//! A puzzle is a board as read by [`crate::parse::parse`], preceded by header lines:
+//! `# comments`, `expect: <moves>` listing the acceptable answers and `forbid: <moves>`
+//! listing losing ones. Ruleset settings can be overridden with `hazard-damage: <n>`.
+
+use std::{fs, path::Path};
+
+use crate::{
+    logic::{self, Weights},
+    parse::parse,
+    render::Grid,
+    requests::Turn,
+    responses::Movement,
+};
+
+struct Puzzle {
+    name: String,
+    turn: Turn,
+    expect: Vec<Movement>,
+    forbid: Vec<Movement>,
+}
+
+impl Puzzle {
+    fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut expect = Vec::new();
+        let mut forbid = Vec::new();
+        let mut hazard_damage = None;
+        let mut board = String::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let moves = |value: &str| {
+                value
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<Vec<Movement>, _>>()
+            };
+            match line.split_once(':') {
+                Some(("expect", value)) => expect = moves(value)?,
+                Some(("forbid", value)) => forbid = moves(value)?,
+                Some(("hazard-damage", value)) => {
+                    hazard_damage = Some(value.trim().parse().map_err(|_| "bad hazard-damage")?)
+                }
+                Some((key, _)) => return Err(format!("unknown key {}", key)),
+                None => {
+                    board += line;
+                    board.push('\n');
+                }
+            }
+        }
+
+        if expect.is_empty() && forbid.is_empty() {
+            return Err("nothing to check, add expect: or forbid:".to_string());
+        }
+
+        let mut turn = parse(&board)?;
+        if let Some(damage) = hazard_damage {
+            turn.game.ruleset.settings.hazard_damage_per_turn = damage;
+        }
+
+        Ok(Self {
+            name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+            turn,
+            expect,
+            forbid,
+        })
+    }
+
+    /// Describes what went wrong, if anything.
+    fn check(&self) -> Option<String> {
+        let search = logic::search(self.turn.clone(), Weights::default());
+        let chosen = search.movement;
+
+        let wrong = if !self.expect.is_empty() && !self.expect.contains(&chosen) {
+            format!("expected one of {:?}", self.expect)
+        } else if self.forbid.contains(&chosen) {
+            format!("forbidden {:?}", self.forbid)
+        } else {
+            return None;
+        };
+
+        Some(format!(
+            "{}: played {:?}, {}\ncandidates {:?}\n{}",
+            self.name,
+            chosen,
+            wrong,
+            search.candidates,
+            Grid::from_turn(&self.turn).with_move(chosen)
+        ))
+    }
+}
+
+#[test]
+fn puzzles() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("puzzles");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no puzzles in {}", dir.display());
+
+    let failures: Vec<_> = paths
+        .iter()
+        .filter_map(|path| match Puzzle::load(path) {
+            Ok(puzzle) => puzzle.check(),
+            Err(e) => Some(format!("{}: {}", path.display(), e)),
+        })
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} puzzles failed\n\n{}",
+        failures.len(),
+        paths.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,276 @@
+use std::fmt::Display;
+
+use crate::{
+    logic::State,
+    requests::{Point, Turn},
+    responses::Movement,
+};
+
+// ANSI foreground per snake, we are always the first
+const COLORS: &[&str] = &["32", "31", "34", "33", "35", "36"];
+const FOOD_COLOR: &str = "91";
+const ARROW_COLOR: &str = "1;97";
+const HAZARD_BACKGROUND: &str = "100";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Cell {
+    Empty,
+    Food,
+    Head(usize),
+    Body(usize),
+    Arrow(Movement),
+}
+
+/// A board laid out for printing, built from a request or a search state.
+///
+/// Snakes are lettered in order, starting with us: heads are upper case and bodies lower
+/// case. Food is `*`, hazards `~` and an arrow marks the move we are about to make. The
+/// top row is the highest `y`, matching the engine's orientation.
+pub struct Grid {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+    hazards: Vec<bool>,
+    legend: Vec<String>,
+}
+
+impl Grid {
+    fn new(width: i32, height: i32) -> Self {
+        let size = (width * height).max(0) as usize;
+        Self {
+            width,
+            height,
+            cells: vec![Cell::Empty; size],
+            hazards: vec![false; size],
+            legend: Vec::new(),
+        }
+    }
+
+    pub fn from_turn(turn: &Turn) -> Self {
+        let mut grid = Self::new(turn.board.width, turn.board.height);
+
+        for &p in &turn.board.hazards {
+            grid.set_hazard(p);
+        }
+        for &p in &turn.board.food {
+            grid.set(p, Cell::Food);
+        }
+
+        let others = turn.board.snakes.iter().filter(|s| s.id != turn.you.id);
+        for (i, snake) in std::iter::once(&turn.you).chain(others).enumerate() {
+            grid.add_snake(i, &snake.body);
+            grid.legend.push(format!(
+                "{} {} health {} length {}",
+                letter(i, true),
+                snake.name,
+                snake.health,
+                snake.body.len()
+            ));
+        }
+
+        grid
+    }
+
+    pub(crate) fn from_state(state: &State) -> Self {
+        let mut grid = Self::new(state.width, state.height);
+
+        for &p in &state.hazards {
+            grid.set_hazard(p);
+        }
+        for &p in &state.food {
+            grid.set(p, Cell::Food);
+        }
+
+        grid.add_snake(0, &state.snake.get_body());
+        grid.legend.push(format!(
+            "{} us health {} length {}",
+            letter(0, true),
+            state.health,
+            state.snake.len()
+        ));
+        for (i, snake) in state.other_snakes.iter().enumerate() {
+            grid.add_snake(i + 1, &snake.get_body());
+            grid.legend
+                .push(format!("{} length {}", letter(i + 1, true), snake.len()));
+        }
+
+        grid
+    }
+
+    /// Marks where our head goes with `movement`.
+    pub fn with_move(mut self, movement: Movement) -> Self {
+        let head = self.cells.iter().position(|&c| c == Cell::Head(0));
+        if let Some(i) = head {
+            let (x, y) = (i as i32 % self.width, i as i32 / self.width);
+            let (x, y) = match movement {
+                Movement::Right => (x + 1, y),
+                Movement::Left => (x - 1, y),
+                Movement::Up => (x, y + 1),
+                Movement::Down => (x, y - 1),
+            };
+            self.set(Point { x, y }, Cell::Arrow(movement));
+        }
+        self
+    }
+
+    /// The board with row and column labels and a legend, ANSI colored if `color`.
+    pub fn render(&self, color: bool) -> String {
+        let mut out = String::from("  ");
+        for x in 0..self.width {
+            out += &format!("{:>2}", x);
+        }
+        out.push('\n');
+
+        for y in (0..self.height).rev() {
+            out += &format!("{:>2}", y);
+            for x in 0..self.width {
+                let i = (y * self.width + x) as usize;
+                let (symbol, foreground) = match self.cells[i] {
+                    Cell::Empty if self.hazards[i] => ('~', None),
+                    Cell::Empty => ('.', None),
+                    Cell::Food => ('*', Some(FOOD_COLOR)),
+                    Cell::Head(s) => (letter(s, true), Some(COLORS[s % COLORS.len()])),
+                    Cell::Body(s) => (letter(s, false), Some(COLORS[s % COLORS.len()])),
+                    Cell::Arrow(m) => (arrow(m), Some(ARROW_COLOR)),
+                };
+
+                out.push(' ');
+                if !color {
+                    out.push(symbol);
+                    continue;
+                }
+
+                let codes: Vec<_> = foreground
+                    .into_iter()
+                    .chain(self.hazards[i].then_some(HAZARD_BACKGROUND))
+                    .collect();
+                if codes.is_empty() {
+                    out.push(symbol);
+                } else {
+                    out += &format!("\x1b[{}m{}\x1b[0m", codes.join(";"), symbol);
+                }
+            }
+            out.push('\n');
+        }
+
+        for line in &self.legend {
+            out += line;
+            out.push('\n');
+        }
+
+        out
+    }
+
+    fn index(&self, p: Point) -> Option<usize> {
+        let inside = p.x >= 0 && p.x < self.width && p.y >= 0 && p.y < self.height;
+        inside.then(|| (p.y * self.width + p.x) as usize)
+    }
+
+    fn set(&mut self, p: Point, cell: Cell) {
+        if let Some(i) = self.index(p) {
+            self.cells[i] = cell;
+        }
+    }
+
+    fn set_hazard(&mut self, p: Point) {
+        if let Some(i) = self.index(p) {
+            self.hazards[i] = true;
+        }
+    }
+
+    fn add_snake(&mut self, snake: usize, body: &[Point]) {
+        // Tail first so a stacked body never hides the head
+        for &p in body.iter().skip(1).rev() {
+            self.set(p, Cell::Body(snake));
+        }
+        if let Some(&head) = body.first() {
+            self.set(head, Cell::Head(snake));
+        }
+    }
+}
+
+impl Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+fn letter(snake: usize, head: bool) -> char {
+    let base = if head { b'A' } else { b'a' };
+    (base + (snake % 26) as u8) as char
+}
+
+fn arrow(movement: Movement) -> char {
+    match movement {
+        Movement::Right => '>',
+        Movement::Left => '<',
+        Movement::Up => '^',
+        Movement::Down => 'v',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::requests::{Board, Game, Snake};
+
+    use super::*;
+
+    fn snake(id: &str, body: &[(i32, i32)]) -> Snake {
+        let body: Vec<_> = body.iter().map(|&(x, y)| Point { x, y }).collect();
+        Snake {
+            id: id.to_string(),
+            name: id.to_string(),
+            health: 90,
+            head: body[0],
+            length: body.len() as u32,
+            body,
+            shout: String::new(),
+            squad: String::new(),
+            latency: 0,
+        }
+    }
+
+    fn turn() -> Turn {
+        let you = snake("you", &[(1, 1), (1, 0), (0, 0)]);
+        Turn {
+            game: Game {
+                id: "render".to_string(),
+                ruleset: Default::default(),
+                timeout: 500,
+            },
+            turn: 3,
+            board: Board {
+                height: 4,
+                width: 4,
+                food: vec![Point { x: 3, y: 3 }],
+                snakes: vec![snake("them", &[(3, 1), (3, 0)]), you.clone()],
+                hazards: vec![Point { x: 0, y: 3 }, Point { x: 3, y: 3 }],
+            },
+            you,
+        }
+    }
+
+    #[test]
+    fn render_plain() {
+        let grid = Grid::from_turn(&turn()).with_move(Movement::Up);
+
+        let expected = "   0 1 2 3\n\
+                        \x203 ~ . . *\n\
+                        \x202 . ^ . .\n\
+                        \x201 . A . B\n\
+                        \x200 a a . b\n\
+                        A you health 90 length 3\n\
+                        B them health 90 length 2\n";
+        assert_eq!(grid.to_string(), expected);
+    }
+
+    #[test]
+    fn render_color() {
+        let rendered = Grid::from_turn(&turn()).render(true);
+
+        // Food on a hazard keeps both colors
+        assert!(rendered.contains("\x1b[91;100m*\x1b[0m"));
+        assert!(rendered.contains("\x1b[32mA\x1b[0m"));
+        assert!(rendered.contains("\x1b[31mB\x1b[0m"));
+    }
+}
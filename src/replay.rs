@@ -0,0 +1,304 @@
+use std::{io::IsTerminal, path::PathBuf};
+
+use crate::{
+    gamelog::{self, Entry},
+    logic::{self, Candidate, TieBreak, Weights},
+    render::Grid,
+    requests::Turn,
+    responses::Movement,
+};
+
+pub struct Options {
+    pub log: PathBuf,
+    /// Weights to replay with, defaults when unset
+    pub weights: Option<PathBuf>,
+    /// Seed to replay with instead of the one the log recorded
+    pub seed: Option<u64>,
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut log = None;
+        let mut weights = None;
+        let mut seed = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--weights" => {
+                    weights = Some(args.next().ok_or("missing value for --weights")?.into())
+                }
+                "--seed" => {
+                    seed = Some(
+                        args.next()
+                            .and_then(|v| v.parse().ok())
+                            .ok_or("--seed takes a number")?,
+                    )
+                }
+                _ if log.is_none() && !arg.starts_with("--") => log = Some(arg.into()),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        Ok(Self {
+            log: log.ok_or("missing game log")?,
+            weights,
+            seed,
+        })
+    }
+}
+
+/// A turn where the current search disagrees with the recording.
+#[derive(Debug)]
+pub struct Change {
+    pub request: Turn,
+    pub recorded: Movement,
+    pub replayed: Movement,
+    pub recorded_candidates: Vec<Candidate>,
+    pub replayed_candidates: Vec<Candidate>,
+}
+
+/// Re-runs the search on every recorded move to the depth and with the tie break it was
+/// served with, returning the number of moves replayed and the ones that came out differently.
+pub fn replay(entries: &[Entry], weights: Weights) -> (usize, Vec<Change>) {
+    let mut moves = 0;
+    let mut changes = Vec::new();
+    let mut tie_break = TieBreak::default();
+
+    for entry in entries {
+        let (request, chosen, candidates, depth) = match entry {
+            Entry::Start { tie_break: t, .. } => {
+                tie_break = *t;
+                continue;
+            }
+            Entry::Move {
+                request,
+                chosen,
+                candidates,
+                depth,
+                ..
+            } => (request, chosen, candidates, *depth),
+            Entry::End { .. } => continue,
+        };
+
+        moves += 1;
+        let search = logic::search_to(request.clone(), weights, tie_break, depth);
+        if search.movement != *chosen {
+            changes.push(Change {
+                request: request.clone(),
+                recorded: *chosen,
+                replayed: search.movement,
+                recorded_candidates: candidates.clone(),
+                replayed_candidates: search.candidates,
+            });
+        }
+    }
+
+    (moves, changes)
+}
+
+/// Replays the log and prints every changed decision, returning how many changed.
+pub fn run(options: &Options) -> Result<usize, Box<dyn std::error::Error>> {
+    let weights = match &options.weights {
+        Some(path) => Weights::load(path)
+            .map_err(|e| format!("failed to load weights {}: {}", path.display(), e))?,
+        None => Weights::default(),
+    };
+    let entries = gamelog::read(&options.log)
+        .map_err(|e| format!("failed to read {}: {}", options.log.display(), e))?;
+    // Searches seeded the way the game was served replay it exactly
+    if let Some(seed) = options.seed.or_else(|| recorded_seed(&entries)) {
+        if !logic::force_seed(seed) {
+            return Err("searches were already seeded".into());
+        }
+    }
+
+    let (moves, changes) = replay(&entries, weights);
+    let color = std::io::stdout().is_terminal();
+
+    for change in &changes {
+        println!(
+            "turn {}: recorded {:?}, now {:?}",
+            change.request.turn, change.recorded, change.replayed
+        );
+        print!(
+            "{}",
+            Grid::from_turn(&change.request)
+                .with_move(change.replayed)
+                .render(color)
+        );
+        println!(
+            "  recorded: {}",
+            format_candidates(&change.recorded_candidates)
+        );
+        println!(
+            "  replayed: {}",
+            format_candidates(&change.replayed_candidates)
+        );
+    }
+    println!("{}/{} decisions changed", changes.len(), moves);
+
+    Ok(changes.len())
+}
+
+/// The seed the game was served with, if it was forced.
+fn recorded_seed(entries: &[Entry]) -> Option<u64> {
+    entries.iter().find_map(|entry| match entry {
+        Entry::Start { seed, .. } => *seed,
+        _ => None,
+    })
+}
+
+fn format_candidates(candidates: &[Candidate]) -> String {
+    candidates
+        .iter()
+        .map(|c| format!("{:?} {}", c.movement, c.score))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        engine::{Game, Rules},
+        logic::Progress,
+        requests::RulesetSettings,
+    };
+
+    use super::*;
+
+    #[test]
+    fn detects_changed_decisions() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let names = ["us".to_string(), "them".to_string()];
+        let game = Game::new(
+            "replay".to_string(),
+            7,
+            7,
+            Rules::Standard,
+            RulesetSettings::default(),
+            &names,
+            &mut rng,
+        );
+        let request = game.turn_for("snake-0").unwrap();
+        let search = logic::search_to(request.clone(), Weights::default(), TieBreak::default(), 4);
+        let other = logic::safe_moves(request.clone())
+            .into_iter()
+            .find(|&m| m != search.movement)
+            .unwrap();
+
+        let entries = vec![
+            Entry::Start {
+                request: request.clone(),
+                seed: Some(3),
+                tie_break: TieBreak::default(),
+            },
+            Entry::Move {
+                request: request.clone(),
+                chosen: search.movement,
+                candidates: search.candidates.clone(),
+                depth: search.depth,
+                stats: search.stats,
+                latency_us: 0,
+            },
+            Entry::Move {
+                request: request.clone(),
+                chosen: other,
+                candidates: vec![],
+                depth: search.depth,
+                stats: search.stats,
+                latency_us: 0,
+            },
+        ];
+
+        let (moves, changes) = replay(&entries, Weights::default());
+
+        assert_eq!(moves, 2);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].recorded, other);
+        assert_eq!(changes[0].replayed, search.movement);
+        assert_eq!(recorded_seed(&entries), Some(3));
+        assert_eq!(recorded_seed(&entries[1..]), None);
+    }
+
+    #[test]
+    fn replays_served_moves() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let names = ["us".to_string(), "them".to_string()];
+        let game = Game::new(
+            "served".to_string(),
+            7,
+            7,
+            Rules::Standard,
+            RulesetSettings::default(),
+            &names,
+            &mut rng,
+        );
+        let request = game.turn_for("snake-0").unwrap();
+
+        // Served the way the server does, deepening until the deadline
+        for threads in [1, 2] {
+            let deadline = Instant::now() + Duration::from_millis(50);
+            let served = logic::search_until(
+                request.clone(),
+                Weights::default(),
+                TieBreak::Random,
+                deadline,
+                threads,
+                &Progress::default(),
+            )
+            .unwrap();
+
+            let entries = vec![
+                Entry::Start {
+                    request: request.clone(),
+                    seed: None,
+                    tie_break: TieBreak::Random,
+                },
+                Entry::Move {
+                    request: request.clone(),
+                    chosen: served.movement,
+                    candidates: served.candidates.clone(),
+                    depth: served.depth,
+                    stats: served.stats,
+                    latency_us: 0,
+                },
+            ];
+            let (moves, changes) = replay(&entries, Weights::default());
+            assert_eq!(moves, 1);
+            assert!(changes.is_empty());
+
+            // Tied lines can come out differently when the root was split across threads
+            let replayed = logic::search_to(
+                request.clone(),
+                Weights::default(),
+                TieBreak::Random,
+                served.depth,
+            );
+            let scores = |search: &logic::Search| {
+                Vec::from_iter(search.candidates.iter().map(|c| (c.movement, c.score)))
+            };
+            assert_eq!(scores(&replayed), scores(&served));
+            if threads == 1 {
+                assert_eq!(replayed.candidates, served.candidates);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_args() {
+        let options = Options::parse(
+            ["game.jsonl", "--weights", "w.json"]
+                .map(String::from)
+                .into_iter(),
+        )
+        .unwrap();
+
+        assert_eq!(options.log, PathBuf::from("game.jsonl"));
+        assert_eq!(options.weights, Some(PathBuf::from("w.json")));
+        assert!(Options::parse(std::iter::empty()).is_err());
+    }
+}
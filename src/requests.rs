@@ -1,8 +1,8 @@
 use std::fmt::Display;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, PartialEq, Eq, Debug, Copy, Clone, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone, PartialOrd, Ord, Hash)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -20,7 +20,7 @@ impl Point {
     }
 }
 
-#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Turn {
     pub game: Game,
     pub turn: u32,
@@ -28,13 +28,58 @@ pub struct Turn {
     pub you: Snake,
 }
 
-#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Game {
     pub id: String,
+    #[serde(default)]
+    pub ruleset: Ruleset,
     pub timeout: i32,
 }
 
-#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct Ruleset {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub settings: RulesetSettings,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RulesetSettings {
+    pub food_spawn_chance: u32,
+    pub minimum_food: u32,
+    pub hazard_damage_per_turn: u32,
+    pub royale: RoyaleSettings,
+}
+
+impl Default for RulesetSettings {
+    // Standard ruleset defaults, used when the engine omits settings.
+    fn default() -> Self {
+        Self {
+            food_spawn_chance: 15,
+            minimum_food: 1,
+            hazard_damage_per_turn: 14,
+            royale: RoyaleSettings::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RoyaleSettings {
+    pub shrink_every_n_turns: u32,
+}
+
+impl Default for RoyaleSettings {
+    fn default() -> Self {
+        Self {
+            shrink_every_n_turns: 25,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Board {
     pub height: i32,
     pub width: i32,
@@ -43,7 +88,7 @@ pub struct Board {
     pub hazards: Vec<Point>,
 }
 
-#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Snake {
     pub id: String,
     pub name: String,
@@ -53,10 +98,38 @@ pub struct Snake {
     pub length: u32,
     pub shout: String,
     pub squad: String,
-    pub latency: String,
+    /// Milliseconds the engine waited for this snake's previous move
+    #[serde(with = "latency")]
+    pub latency: u32,
+}
+
+// The engine sends latency as a string, empty before the first move. Anything that isn't
+// a number counts as no latency rather than failing the whole request.
+mod latency {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(latency: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&latency.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u32),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(latency) => Ok(latency),
+            Raw::Text(text) => Ok(text.parse().unwrap_or(0)),
+        }
+    }
 }
 
 #[cfg(test)]
+// Written before the code was linted, kept as they were
+#[allow(clippy::redundant_field_names, clippy::assertions_on_constants)]
 mod test {
     use super::*;
 
@@ -67,7 +140,12 @@ mod test {
               "id": "game-00fe20da-94ad-11ea-bb37",
               "ruleset": {
                 "name": "standard",
-                "version": "v.1.2.3"
+                "version": "v.1.2.3",
+                "settings": {
+                  "foodSpawnChance": 25,
+                  "minimumFood": 2,
+                  "hazardDamagePerTurn": 14
+                }
               },
               "timeout": 500
             },
@@ -136,6 +214,16 @@ mod test {
 
         let game = Game {
             id: "game-00fe20da-94ad-11ea-bb37".to_string(),
+            ruleset: Ruleset {
+                name: "standard".to_string(),
+                version: "v.1.2.3".to_string(),
+                settings: RulesetSettings {
+                    food_spawn_chance: 25,
+                    minimum_food: 2,
+                    hazard_damage_per_turn: 14,
+                    ..Default::default()
+                },
+            },
             timeout: 500,
         };
 
@@ -148,7 +236,7 @@ mod test {
                 Point { x: 1, y: 0 },
                 Point { x: 2, y: 0 },
             ],
-            latency: "111".to_string(),
+            latency: 111,
             head: Point { x: 0, y: 0 },
             length: 3,
             shout: "why are we shouting??".to_string(),
@@ -167,7 +255,7 @@ mod test {
             ],
             length: 4,
             head: Point { x: 5, y: 4 },
-            latency: "222".to_string(),
+            latency: 222,
             shout: "I'm not really sure...".to_string(),
             squad: "".to_string(),
         };
@@ -204,4 +292,31 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn latency_formats() {
+        let snake = |latency: &str| {
+            let json = format!(
+                r#"{{"id": "a", "name": "a", "health": 90, "body": [], "head": {{"x": 0, "y": 0}},
+                    "length": 0, "shout": "", "squad": "", "latency": {}}}"#,
+                latency
+            );
+            serde_json::from_str::<Snake>(&json).map(|s| s.latency)
+        };
+
+        assert_eq!(snake(r#""123""#).unwrap(), 123);
+        assert_eq!(snake(r#""""#).unwrap(), 0);
+        assert_eq!(snake("45").unwrap(), 45);
+        assert_eq!(snake(r#""slow""#).unwrap(), 0);
+
+        let mut you = serde_json::from_str::<Snake>(
+            r#"{"id": "a", "name": "a", "health": 90, "body": [], "head": {"x": 0, "y": 0},
+                "length": 0, "shout": "", "squad": "", "latency": "7"}"#,
+        )
+        .unwrap();
+        you.latency = 80;
+        assert!(serde_json::to_string(&you)
+            .unwrap()
+            .contains(r#""latency":"80""#));
+    }
 }
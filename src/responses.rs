@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Debug)]
@@ -8,9 +10,9 @@ pub struct Info {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub color: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub head: Option<String>,
+    pub head: Option<HeadType>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tail: Option<String>,
+    pub tail: Option<TailType>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
 }
@@ -34,11 +36,14 @@ impl Start {
     }
 }
 
-// TODO: Make all the head types
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
-#[serde(rename_all = "lowercase")]
+/// Heads the engine can draw, as named in its customization guide.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
 pub enum HeadType {
-    Regular,
+    // Called regular before API version 1
+    #[default]
+    #[serde(alias = "regular")]
+    Default,
     Beluga,
     Bendr,
     Dead,
@@ -46,33 +51,71 @@ pub enum HeadType {
     Fang,
     Pixel,
     Safe,
-    #[serde(rename = "sand-worm")]
     SandWorm,
     Shades,
+    Silly,
     Smile,
     Tongue,
+    Alligator,
+    Bonhomme,
+    Caffeine,
+    Comet,
+    CosmicHorror,
+    Earmuffs,
+    Football,
+    Gamer,
+    Iguana,
+    LanternFish,
+    Mask,
+    Missile,
+    MotoHelmet,
+    Orca,
+    RocketHelmet,
+    Rudolph,
+    Scarf,
+    Ski,
+    Snail,
+    SnowWorm,
+    TigerKing,
+    Villain,
+    Workout,
 }
 
-// TODO: Make all the tail types
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
-#[serde(rename_all = "lowercase")]
+/// Tails the engine can draw, as named in its customization guide.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
 pub enum TailType {
-    Regular,
-    #[serde(rename = "block-bum")]
+    // Called regular before API version 1
+    #[default]
+    #[serde(alias = "regular")]
+    Default,
     BlockBum,
     Bolt,
     Curled,
-    #[serde(rename = "fat-rattle")]
     FatRattle,
     Freckled,
     Hook,
     Pixel,
-    #[serde(rename = "round-bum")]
     RoundBum,
     Sharp,
     Skinny,
-    #[serde(rename = "small-rattle")]
     SmallRattle,
+    Alligator,
+    Bonhomme,
+    Coffee,
+    Comet,
+    Flake,
+    Gamer,
+    Ghost,
+    IceSkate,
+    Iguana,
+    Mouse,
+    MysticMoon,
+    Present,
+    Rocket,
+    Shiny,
+    TigerTail,
+    Weight,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
@@ -83,6 +126,9 @@ pub struct Move {
     shout: Option<String>,
 }
 
+// Longer shouts are cut by the engine
+pub const MAX_SHOUT: usize = 256;
+
 impl Move {
     pub fn new(movement: Movement) -> Move {
         Move {
@@ -90,9 +136,15 @@ impl Move {
             shout: None,
         }
     }
+
+    /// Adds a shout, cut to `MAX_SHOUT` characters.
+    pub fn with_shout(mut self, shout: Option<String>) -> Move {
+        self.shout = shout.map(|s| s.chars().take(MAX_SHOUT).collect());
+        self
+    }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Copy, Clone, Hash)]
 #[serde(rename_all(serialize = "lowercase", deserialize = "lowercase"))]
 pub enum Movement {
     Right = 0,
@@ -113,7 +165,26 @@ impl From<usize> for Movement {
     }
 }
 
+impl FromStr for Movement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right" => Ok(Self::Right),
+            "left" => Ok(Self::Left),
+            "up" => Ok(Self::Up),
+            "down" => Ok(Self::Down),
+            _ => Err(format!(
+                "unknown move {}, expected up, down, left or right",
+                s
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
+// Written before the code was linted, kept as they were
+#[allow(clippy::assertions_on_constants, clippy::needless_borrow)]
 mod test {
     use super::*;
 
@@ -141,6 +212,23 @@ mod test {
         }
     }
 
+    #[test]
+    fn serialize_info() {
+        let response = Info {
+            apiversion: "1".to_string(),
+            author: None,
+            color: Some("#b7410e".to_string()),
+            head: Some(HeadType::SandWorm),
+            tail: Some(TailType::Default),
+            version: None,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            "{\"apiversion\":\"1\",\"color\":\"#b7410e\",\"head\":\"sand-worm\",\"tail\":\"default\"}"
+        );
+    }
+
     #[test]
     fn serialize_move() {
         let response = Move {
@@ -161,6 +249,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn shouts_are_capped() {
+        let response = Move::new(Movement::Up).with_shout(Some("ä".repeat(300)));
+        assert_eq!(response.shout.unwrap().chars().count(), MAX_SHOUT);
+
+        let response = Move::new(Movement::Up).with_shout(Some("hi".to_string()));
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            "{\"move\":\"up\",\"shout\":\"hi\"}"
+        );
+    }
+
     #[test]
     fn deserialize_start() {
         let string = "{\"color\":\"#ff00ff\",\"headType\":\"bendr\",\"tailType\":\"pixel\"}";
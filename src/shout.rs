@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::{
+    logic::{self, Search},
+    requests::Turn,
+};
+
+/// When the snake shouts.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Shouts {
+    pub enabled: bool,
+    /// Turns at least between two shouts in a game
+    pub every: u32,
+    /// Searches shallower than this are not worth bragging about
+    pub min_depth: u8,
+}
+
+impl Default for Shouts {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            every: 5,
+            min_depth: 8,
+        }
+    }
+}
+
+/// What one game has been told so far. Wins, losses and each trapped snake are called out
+/// once, depth only when it beats the deepest search of the game.
+#[derive(Default, Debug)]
+pub struct Shouter {
+    last: Option<u32>,
+    won: bool,
+    lost: bool,
+    taunted: HashSet<String>,
+    deepest: u8,
+}
+
+impl Shouter {
+    /// Something to say about the search for `turn`, if anything is due.
+    pub fn shout(&mut self, settings: &Shouts, turn: &Turn, search: &Search) -> Option<String> {
+        if !settings.enabled {
+            return None;
+        }
+        if self
+            .last
+            .is_some_and(|last| turn.turn.saturating_sub(last) < settings.every)
+        {
+            return None;
+        }
+
+        let chosen = search
+            .candidates
+            .iter()
+            .find(|c| c.movement == search.movement);
+
+        // Cornering one of several snakes doesn't win the game yet
+        let last_one = turn.board.snakes.len() == 2;
+        let shout = if !self.won && last_one && chosen.is_some_and(|c| c.wins()) {
+            self.won = true;
+            format!("Forced win within {} plies", search.depth)
+        } else if !self.lost
+            && !search.candidates.is_empty()
+            && search.candidates.iter().all(|c| c.loses())
+        {
+            self.lost = true;
+            "Well played".to_string()
+        } else if let Some(name) = logic::trapped(turn.clone())
+            .into_iter()
+            .find(|name| !self.taunted.contains(name))
+        {
+            self.taunted.insert(name.clone());
+            format!("{}, nowhere left to go", name)
+        } else if search.depth >= settings.min_depth && search.depth > self.deepest {
+            self.deepest = search.depth;
+            format!("Searched {} plies ahead", search.depth)
+        } else {
+            return None;
+        };
+
+        self.last = Some(turn.turn);
+        Some(shout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        logic::{Candidate, Stats},
+        parse::parse,
+        responses::Movement,
+    };
+
+    use super::*;
+
+    fn search(depth: u8, score: isize) -> Search {
+        Search {
+            movement: Movement::Up,
+            candidates: vec![Candidate {
+                movement: Movement::Up,
+                score,
+                pv: Vec::new(),
+            }],
+            depth,
+            stats: Stats::default(),
+        }
+    }
+
+    #[test]
+    fn depth_records_are_rate_limited() {
+        let settings = Shouts::default();
+        let mut shouter = Shouter::default();
+        let mut turn = parse("...\n.A.\n.a.\n...\n..B").unwrap();
+
+        assert_eq!(shouter.shout(&settings, &turn, &search(6, 0)), None);
+        assert_eq!(
+            shouter.shout(&settings, &turn, &search(8, 0)).as_deref(),
+            Some("Searched 8 plies ahead")
+        );
+
+        turn.turn += settings.every - 1;
+        assert_eq!(shouter.shout(&settings, &turn, &search(10, 0)), None);
+        turn.turn += 1;
+        assert!(shouter.shout(&settings, &turn, &search(10, 0)).is_some());
+        turn.turn += settings.every;
+        assert_eq!(shouter.shout(&settings, &turn, &search(10, 0)), None);
+
+        let off = Shouts {
+            enabled: false,
+            ..settings
+        };
+        assert_eq!(Shouter::default().shout(&off, &turn, &search(12, 0)), None);
+    }
+
+    #[test]
+    fn wins_losses_and_taunts_once() {
+        let settings = Shouts {
+            every: 0,
+            ..Default::default()
+        };
+        let mut shouter = Shouter::default();
+        // B is boxed in by the walls, its own body and ours
+        let turn = parse("Bbb.\naaA.\na...\n....\nB them").unwrap();
+
+        assert_eq!(
+            shouter.shout(&settings, &turn, &search(4, isize::MAX)),
+            Some("Forced win within 4 plies".to_string())
+        );
+        assert_eq!(
+            shouter.shout(&settings, &turn, &search(4, isize::MAX)),
+            Some("them, nowhere left to go".to_string())
+        );
+        assert_eq!(
+            shouter.shout(&settings, &turn, &search(4, isize::MIN)),
+            Some("Well played".to_string())
+        );
+        assert_eq!(
+            shouter.shout(&settings, &turn, &search(4, isize::MIN)),
+            None
+        );
+
+        // C is still around after B is cornered
+        let turn = parse("Bbb.\naaA.\na...\n...C\nB them").unwrap();
+        assert_eq!(
+            Shouter::default().shout(&settings, &turn, &search(4, isize::MAX)),
+            Some("them, nowhere left to go".to_string())
+        );
+    }
+}
@@ -2,6 +2,7 @@ use std::{collections::VecDeque, fmt::Debug};
 
 use crate::requests::Point;
 
+#[derive(Clone, Hash)]
 pub struct Snake {
     body: VecDeque<Point>,
 }
@@ -39,6 +40,10 @@ impl Snake {
         self.body[0]
     }
 
+    pub fn len(&self) -> usize {
+        self.body.len()
+    }
+
     pub fn collides_with(&self, p: Point) -> bool {
         for i in 0..self.body.len() - 1 {
             if self.body[i] == p {
@@ -52,13 +57,14 @@ impl Snake {
     pub fn set_unchecked(&mut self, index: usize, p: Point) {
         self.body[index] = p;
     }
-    #[cfg(test)]
     pub fn get_body(&self) -> Vec<Point> {
         self.body.iter().cloned().collect()
     }
 }
 
 #[cfg(test)]
+// Written before the code was linted, kept as they were
+#[allow(clippy::useless_vec, clippy::iter_cloned_collect)]
 mod tests {
 
     use super::*;
@@ -2,6 +2,7 @@ use std::fmt::Debug;
 
 use crate::requests::Point;
 
+#[derive(Clone, Hash)]
 pub struct Snake {
     is_first: bool,
     head_index: usize,
@@ -62,6 +63,10 @@ impl Snake {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.body.len()
+    }
+
     pub fn collides_with(&self, p: Point) -> bool {
         let tail_index = self
             .head_index
@@ -81,13 +86,14 @@ impl Snake {
     pub fn set_unchecked(&mut self, index: usize, p: Point) {
         self.body[index] = p;
     }
-    #[cfg(test)]
     pub fn get_body(&self) -> Vec<Point> {
         self.body.clone()
     }
 }
 
 #[cfg(test)]
+// Written before the code was linted, kept as they were
+#[allow(clippy::useless_vec, clippy::iter_cloned_collect)]
 mod tests {
 
     use super::*;
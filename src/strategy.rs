@@ -0,0 +1,90 @@
+use rand::seq::SliceRandom;
+
+use crate::{
+    logic::{self, Search, Weights},
+    requests::Turn,
+    responses::{Move, Movement},
+};
+
+/// Something that can pick a move for a turn, registered by name in [`NAMES`].
+pub trait Strategy: Send + Sync {
+    fn get_move(&self, turn: Turn) -> Move;
+
+    /// The full search behind the move, for strategies that search.
+    fn search(&self, _turn: Turn) -> Option<Search> {
+        None
+    }
+}
+
+pub const NAMES: &[&str] = &["minimax", "random"];
+
+/// The minimax search with a given set of evaluation weights.
+pub struct Minimax {
+    pub weights: Weights,
+}
+
+impl Strategy for Minimax {
+    fn get_move(&self, turn: Turn) -> Move {
+        logic::get_move(turn, self.weights)
+    }
+
+    fn search(&self, turn: Turn) -> Option<Search> {
+        Some(logic::search(turn, self.weights))
+    }
+}
+
+/// Any move that doesn't die immediately, a baseline opponent.
+pub struct Random;
+
+impl Strategy for Random {
+    fn get_move(&self, turn: Turn) -> Move {
+        // Seeded from the position so games replay identically
+        let mut rng = logic::rng(&turn);
+
+        let movement = logic::safe_moves(turn)
+            .choose(&mut rng)
+            .copied()
+            .unwrap_or(Movement::Up);
+
+        Move::new(movement)
+    }
+}
+
+/// Builds a strategy from `name[:weights.json]`, the weights file only applies to `minimax`.
+pub fn from_spec(spec: &str) -> Result<Box<dyn Strategy>, String> {
+    let (name, weights) = match spec.split_once(':') {
+        Some((name, path)) => {
+            let weights = Weights::load(path.as_ref())
+                .map_err(|e| format!("failed to load weights {}: {}", path, e))?;
+            (name, Some(weights))
+        }
+        None => (spec, None),
+    };
+
+    match (name, weights) {
+        ("minimax", weights) => Ok(Box::new(Minimax {
+            weights: weights.unwrap_or_default(),
+        })),
+        ("random", None) => Ok(Box::new(Random)),
+        ("random", Some(_)) => Err("random takes no weights".to_string()),
+        _ => Err(format!(
+            "unknown strategy {}, expected one of {}",
+            name,
+            NAMES.join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs() {
+        assert!(from_spec("minimax").is_ok());
+        assert!(from_spec("random").is_ok());
+        assert!(from_spec("random:weights.json").is_err());
+        assert!(from_spec("minimax:does-not-exist.json").is_err());
+        assert!(from_spec("alphazero").is_err());
+    }
+}
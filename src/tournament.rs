@@ -0,0 +1,381 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use serde::Serialize;
+
+use crate::{
+    arena::{self, Outcome},
+    engine::check_board,
+    strategy::{self, Strategy},
+};
+
+// 95% confidence
+const Z: f64 = 1.96;
+
+pub struct Options {
+    /// Board, ruleset and seed settings, `games` is per pairing
+    pub arena: arena::Options,
+    pub threads: usize,
+    pub json: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            arena: arena::Options {
+                games: 10,
+                ..Default::default()
+            },
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            json: None,
+        };
+
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", flag))?;
+            let invalid = |_| format!("invalid value for {}: {}", flag, value);
+
+            match flag.as_str() {
+                "--threads" => options.threads = value.parse().map_err(invalid)?,
+                "--json" => options.json = Some(value.into()),
+                "--log" => return Err("tournaments don't write game logs".to_string()),
+                _ => options.arena.set(&flag, value)?,
+            }
+        }
+
+        if options.arena.snakes.len() < 2 {
+            return Err("a tournament needs at least two --snake".to_string());
+        }
+        // Every game is a single pairing, so any number of entrants fits
+        check_board(options.arena.width, options.arena.height, 2)?;
+
+        Ok(options)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Standing {
+    pub strategy: String,
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// Wins plus half the draws, over games played
+    pub score: f64,
+    pub confidence: (f64, f64),
+    pub elo: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Pairing {
+    pub first: String,
+    pub second: String,
+    pub first_wins: usize,
+    pub second_wins: usize,
+    pub draws: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub standings: Vec<Standing>,
+    pub pairings: Vec<Pairing>,
+}
+
+struct Job {
+    first: usize,
+    second: usize,
+    game: usize,
+}
+
+/// Round robin: every pair of strategies plays `games` games, alternating seats.
+pub fn run(options: &Options) -> Result<Report, Box<dyn std::error::Error>> {
+    let specs = &options.arena.snakes;
+    let strategies = specs
+        .iter()
+        .map(|spec| strategy::from_spec(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let jobs: Vec<_> = (0..specs.len())
+        .flat_map(|first| ((first + 1)..specs.len()).map(move |second| (first, second)))
+        .flat_map(|(first, second)| {
+            (0..options.arena.games).map(move |game| Job {
+                first,
+                second,
+                game,
+            })
+        })
+        .collect();
+
+    let winners = play_all(&strategies, &jobs, options)?;
+
+    let mut pairings: Vec<Pairing> = Vec::new();
+    let mut current = None;
+    for (job, winner) in jobs.iter().zip(&winners) {
+        // Jobs are grouped by pairing, start a new row whenever the pair changes
+        if current != Some((job.first, job.second)) {
+            current = Some((job.first, job.second));
+            pairings.push(Pairing {
+                first: specs[job.first].clone(),
+                second: specs[job.second].clone(),
+                first_wins: 0,
+                second_wins: 0,
+                draws: 0,
+            });
+        }
+
+        let pairing = pairings.last_mut().unwrap();
+        match *winner {
+            Some(w) if w == job.first => pairing.first_wins += 1,
+            Some(_) => pairing.second_wins += 1,
+            None => pairing.draws += 1,
+        }
+    }
+
+    let n = specs.len();
+    // Points scored by i against j, draws count half
+    let mut points = vec![vec![0.0; n]; n];
+    let mut played = vec![vec![0usize; n]; n];
+    for (job, winner) in jobs.iter().zip(&winners) {
+        let (a, b) = (job.first, job.second);
+        played[a][b] += 1;
+        played[b][a] += 1;
+        match *winner {
+            Some(w) if w == a => points[a][b] += 1.0,
+            Some(_) => points[b][a] += 1.0,
+            None => {
+                points[a][b] += 0.5;
+                points[b][a] += 0.5;
+            }
+        }
+    }
+
+    let elo = bradley_terry(&points, &played);
+    let mut standings: Vec<_> = (0..n)
+        .map(|i| {
+            let games: usize = played[i].iter().sum();
+            let wins = (0..jobs.len()).filter(|&g| winners[g] == Some(i)).count();
+            let draws = jobs
+                .iter()
+                .zip(&winners)
+                .filter(|(job, w)| w.is_none() && (job.first == i || job.second == i))
+                .count();
+            let score = points[i].iter().sum::<f64>() / games.max(1) as f64;
+
+            Standing {
+                strategy: specs[i].clone(),
+                games,
+                wins,
+                draws,
+                losses: games - wins - draws,
+                score,
+                confidence: wilson(score, games),
+                elo: elo[i],
+            }
+        })
+        .collect();
+    standings.sort_by(|a, b| b.elo.total_cmp(&a.elo));
+
+    let report = Report {
+        standings,
+        pairings,
+    };
+
+    if let Some(path) = &options.json {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+
+    Ok(report)
+}
+
+/// Plays every job across `options.threads` workers, results line up with `jobs` and map
+/// the winner back to its index in `strategies`.
+fn play_all(
+    strategies: &[Box<dyn Strategy>],
+    jobs: &[Job],
+    options: &Options,
+) -> std::io::Result<Vec<Option<usize>>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::from_iter((0..jobs.len()).map(|_| None)));
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.threads.max(1) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(job) = jobs.get(i) else {
+                    break;
+                };
+
+                // Swap seats every other game so neither side keeps the better start
+                let seats = if job.game % 2 == 0 {
+                    [job.first, job.second]
+                } else {
+                    [job.second, job.first]
+                };
+                let players = seats.map(|s| strategies[s].as_ref());
+                let names = seats.map(|s| options.arena.snakes[s].clone());
+                let seed = options.arena.seed.wrapping_add(i as u64);
+
+                let outcome = arena::play(&players, &names, &options.arena, seed, None)
+                    .map(|Outcome { winner, .. }| winner.map(|w| seats[w]));
+                results.lock().unwrap()[i] = Some(outcome);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every job is played"))
+        .collect()
+}
+
+/// Wilson score interval for a proportion `p` observed over `n` trials.
+fn wilson(p: f64, n: usize) -> (f64, f64) {
+    if n == 0 {
+        return (0.0, 1.0);
+    }
+
+    let n = n as f64;
+    let denominator = 1.0 + Z * Z / n;
+    let centre = (p + Z * Z / (2.0 * n)) / denominator;
+    let half = Z * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt() / denominator;
+
+    ((centre - half).max(0.0), (centre + half).min(1.0))
+}
+
+/// Elo ratings from a Bradley-Terry fit of the pairwise results, averaging 1500.
+///
+/// Every pairing that was played gets one extra virtual draw, so a strategy that never
+/// scores still ends up with a finite rating.
+fn bradley_terry(points: &[Vec<f64>], played: &[Vec<usize>]) -> Vec<f64> {
+    let n = points.len();
+    let prior = |i: usize, j: usize| if played[i][j] > 0 { 1.0 } else { 0.0 };
+    let mut strength = vec![1.0; n];
+
+    for _ in 0..1000 {
+        let next: Vec<f64> = (0..n)
+            .map(|i| {
+                let won: f64 = (0..n).map(|j| points[i][j] + prior(i, j) / 2.0).sum();
+                let expected: f64 = (0..n)
+                    .filter(|&j| j != i)
+                    .map(|j| (played[i][j] as f64 + prior(i, j)) / (strength[i] + strength[j]))
+                    .sum();
+
+                if expected > 0.0 {
+                    won / expected
+                } else {
+                    strength[i]
+                }
+            })
+            .collect();
+
+        // Pin the geometric mean so ratings don't drift
+        let mean = next.iter().map(|s| s.ln()).sum::<f64>() / n as f64;
+        strength = next.iter().map(|s| s / mean.exp()).collect();
+    }
+
+    strength
+        .iter()
+        .map(|s| 1500.0 + 400.0 * s.log10())
+        .collect()
+}
+
+pub fn print(report: &Report) {
+    println!(
+        "{:<30} {:>6} {:>5} {:>5} {:>5} {:>7} {:>15} {:>7}",
+        "strategy", "games", "won", "drawn", "lost", "score", "95% ci", "elo"
+    );
+    for s in &report.standings {
+        println!(
+            "{:<30} {:>6} {:>5} {:>5} {:>5} {:>6.1}% {:>6.1}%-{:>5.1}% {:>7.0}",
+            s.strategy,
+            s.games,
+            s.wins,
+            s.draws,
+            s.losses,
+            s.score * 100.0,
+            s.confidence.0 * 100.0,
+            s.confidence.1 * 100.0,
+            s.elo
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wilson_interval() {
+        let (low, high) = wilson(0.5, 100);
+        assert!((low - 0.404).abs() < 0.001);
+        assert!((high - 0.596).abs() < 0.001);
+
+        assert_eq!(wilson(0.0, 0), (0.0, 1.0));
+        assert_eq!(wilson(1.0, 10).1, 1.0);
+    }
+
+    #[test]
+    fn elo_ranks_by_results() {
+        // 0 beats 1 beats 2, 0 and 2 split evenly
+        let points = vec![
+            vec![0.0, 8.0, 5.0],
+            vec![2.0, 0.0, 8.0],
+            vec![5.0, 2.0, 0.0],
+        ];
+        let played = vec![vec![0, 10, 10], vec![10, 0, 10], vec![10, 10, 0]];
+
+        let elo = bradley_terry(&points, &played);
+
+        assert!(elo[0] > elo[1] && elo[1] > elo[2]);
+        assert!((elo.iter().sum::<f64>() / 3.0 - 1500.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn round_robin() {
+        let args = [
+            "--snake",
+            "random",
+            "--snake",
+            "random",
+            "--snake",
+            "random",
+            "--games",
+            "2",
+            "--width",
+            "7",
+            "--height",
+            "7",
+            "--max-turns",
+            "30",
+            "--threads",
+            "2",
+        ];
+        let options = Options::parse(args.map(String::from).into_iter()).unwrap();
+
+        let report = run(&options).unwrap();
+
+        assert_eq!(report.pairings.len(), 3);
+        assert!(report.standings.iter().all(|s| s.games == 4));
+        let decided: usize = report.standings.iter().map(|s| s.wins + s.draws).sum();
+        let draws: usize = report.pairings.iter().map(|p| p.draws).sum();
+        assert_eq!(decided, 6 + draws);
+    }
+
+    #[test]
+    fn parse_flags() {
+        let parse = |args: &[&str]| Options::parse(args.iter().map(|a| a.to_string()));
+
+        assert!(parse(&["--snake", "random", "--snake", "random", "--width", "0"]).is_err());
+        assert!(parse(&["--snake", "random", "--snake", "random", "--height", "3"]).is_err());
+        assert!(parse(&["--snake", "random"].repeat(9)).is_ok());
+        assert!(parse(&["--snake", "random"]).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::path::PathBuf;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    engine::{self, Game, Rules},
+    logic::{self, Weights},
+    requests::RulesetSettings,
+};
+
+const PARAMS: usize = 8;
+
+pub struct Options {
+    pub iterations: usize,
+    /// Games per iteration, seats alternate between the two weight vectors
+    pub games: usize,
+    pub max_turns: u32,
+    pub seed: u64,
+    pub from: Option<PathBuf>,
+    pub out: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            iterations: 50,
+            games: 10,
+            max_turns: 300,
+            seed: 0,
+            from: None,
+            out: PathBuf::from("weights.json"),
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", flag))?;
+            let invalid = |_| format!("invalid value for {}: {}", flag, value);
+
+            match flag.as_str() {
+                "--iterations" => options.iterations = value.parse().map_err(invalid)?,
+                "--games" => options.games = value.parse().map_err(invalid)?,
+                "--max-turns" => options.max_turns = value.parse().map_err(invalid)?,
+                "--seed" => options.seed = value.parse().map_err(invalid)?,
+                "--from" => options.from = Some(value.into()),
+                "--out" => options.out = value.into(),
+                _ => return Err(format!("unknown flag {}", flag)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Tunes evaluation weights with SPSA: every iteration perturbs all weights at once in a
+/// random direction, plays the two perturbed vectors against each other and steps towards
+/// the winner. The current weights are written to `out` after every iteration.
+pub fn run(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let initial = match &options.from {
+        Some(path) => Weights::load(path)
+            .map_err(|e| format!("failed to load weights {}: {}", path.display(), e))?,
+        None => Weights::default(),
+    };
+
+    let mut theta = to_params(&initial);
+    // Perturbation size per weight, relative to its starting magnitude
+    let steps = theta.map(|t| (t.abs() / 5.0).max(2.0));
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    for iteration in 0..options.iterations {
+        let gain = 1.0 / (iteration as f64 + 1.0).powf(0.602);
+        let delta: [f64; PARAMS] = std::array::from_fn(|_| if rng.gen() { 1.0 } else { -1.0 });
+
+        let plus = from_params(std::array::from_fn(|i| theta[i] + steps[i] * delta[i]));
+        let minus = from_params(std::array::from_fn(|i| theta[i] - steps[i] * delta[i]));
+
+        let seed = options
+            .seed
+            .wrapping_add((iteration * options.games) as u64);
+        let result = play_match(plus, minus, options.games, options.max_turns, seed);
+
+        for i in 0..PARAMS {
+            theta[i] += gain * result * steps[i] * delta[i];
+        }
+
+        let weights = from_params(theta);
+        std::fs::write(&options.out, serde_json::to_string_pretty(&weights)?)?;
+        println!(
+            "iteration {}/{}: plus scored {:+.2}, {:?}",
+            iteration + 1,
+            options.iterations,
+            result,
+            weights
+        );
+    }
+
+    Ok(())
+}
+
+/// Plays `games` self-play games and returns `plus`' score in `[-1, 1]`, draws count as zero.
+pub fn play_match(plus: Weights, minus: Weights, games: usize, max_turns: u32, seed: u64) -> f64 {
+    let mut score = 0.0;
+
+    for g in 0..games {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(g as u64));
+        // Swap seats so neither side keeps the better start
+        let names = if g % 2 == 0 {
+            ["plus".to_string(), "minus".to_string()]
+        } else {
+            ["minus".to_string(), "plus".to_string()]
+        };
+
+        let mut game = Game::new(
+            format!("tune-{}-{}", seed, g),
+            11,
+            11,
+            Rules::Standard,
+            RulesetSettings::default(),
+            &names,
+            &mut rng,
+        );
+
+        engine::run(&mut game, max_turns, &mut rng, |turn| {
+            let weights = if turn.you.name == "plus" { plus } else { minus };
+            logic::get_move(turn, weights).movement
+        });
+
+        score += match game.winner().map(|s| s.name.as_str()) {
+            Some("plus") => 1.0,
+            Some(_) => -1.0,
+            None => 0.0,
+        };
+    }
+
+    score / games.max(1) as f64
+}
+
+fn to_params(weights: &Weights) -> [f64; PARAMS] {
+    [
+        weights.length,
+        weights.enemy_length,
+        weights.health,
+        weights.space,
+        weights.territory,
+        weights.food_distance,
+        weights.hazard,
+        weights.aggression,
+    ]
+    .map(|w| w as f64)
+}
+
+fn from_params(params: [f64; PARAMS]) -> Weights {
+    let [length, enemy_length, health, space, territory, food_distance, hazard, aggression] =
+        params.map(|p| p.round() as isize);
+
+    Weights {
+        length,
+        enemy_length,
+        health,
+        space,
+        territory,
+        food_distance,
+        hazard,
+        aggression,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_round_trip() {
+        let weights = Weights {
+            space: 3,
+            aggression: -2,
+            ..Default::default()
+        };
+
+        assert_eq!(from_params(to_params(&weights)), weights);
+    }
+
+    #[test]
+    fn parse_flags() {
+        let args = ["--games", "4", "--out", "tuned.json"].map(String::from);
+        let options = Options::parse(args.into_iter()).unwrap();
+
+        assert_eq!(options.games, 4);
+        assert_eq!(options.out, PathBuf::from("tuned.json"));
+        assert!(Options::parse(["--games".to_string()].into_iter()).is_err());
+        assert!(Options::parse(["--bogus", "1"].map(String::from).into_iter()).is_err());
+    }
+}